@@ -0,0 +1,22 @@
+use bubblers::{
+    cli_builder,
+    config::{ArgConfig, CliConfig, CommandConfig},
+};
+use std::sync::Arc;
+
+fn main() {
+    let mut cli = CliConfig::new("MyCLI", "1.0", "A CLI reading defaults from config files");
+    cli.load_config_files()
+        .expect("config files should be readable");
+
+    let mut greet = CommandConfig::new_standard(
+        "greet",
+        "Greet someone",
+        Arc::new(|args| println!("Hello, {}!", args.get("name").unwrap_or("world"))),
+    );
+    // Try `config set greet.name Ferris`, then `greet`
+    greet.add_arg(ArgConfig::new("name", "Name to greet").long("name"));
+    cli.add_command(greet);
+
+    cli_builder::execute_cli(&cli);
+}
@@ -1,16 +1,17 @@
 use bubblers::{
     cli_builder,
     config::{ArgConfig, CliConfig, CommandConfig},
+    context::Context,
 };
 use std::sync::Arc;
 
 fn main() {
     // Define actions as functions for better readability and reuse
-    fn echo_action(args: &[String]) {
-        println!("Echo: {}", args.join(" "));
+    fn echo_action(ctx: &Context) {
+        println!("Echo: {}", ctx.join(" "));
     }
 
-    fn version_action(_: &[String]) {
+    fn version_action(_: &Context) {
         println!("Version 1.0.0");
     }
 
@@ -24,9 +25,10 @@ fn main() {
         Arc::new(echo_action),
     );
     echo_command.add_arg(ArgConfig {
-        name: "message",
+        name: "message".into(),
         help: "Message to echo back".to_string(),
         required: true,
+        ..Default::default()
     });
 
     // Add commands to CLI configuration
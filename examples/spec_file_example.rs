@@ -0,0 +1,16 @@
+use bubblers::{cli_builder, config::CliConfig, spec::ActionRegistry};
+use std::{path::Path, sync::Arc};
+
+fn main() {
+    // Actions are written in Rust and referred to by name from the spec
+    let mut registry = ActionRegistry::new();
+    registry.register_standard(
+        "greet",
+        Arc::new(|args| println!("Hello, {}!", args.get("name").unwrap_or("world"))),
+    );
+
+    let spec = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/specs/mycli.toml");
+    let cli = CliConfig::from_spec_file(&spec, &registry).expect("spec file should be valid");
+
+    cli_builder::execute_cli(&cli);
+}
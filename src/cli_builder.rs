@@ -1,89 +1,684 @@
-use crate::config::{CliConfig, CommandType};
-use clap::{Arg, Command};
+use crate::config::{ArgConfig, Args, BareInvocation, CliConfig, CommandConfig};
+use crate::context::{self, Captured, ColorChoice, Context, GlobalFlags, Io, OutputFormat};
+use crate::ui::Prompt;
+use crate::{completions, docs, help, hooks, settings, signals, ui, wrappers};
+use clap::{Arg, ArgAction, ArgGroup, ArgMatches, Command};
+use serde_json::{json, Value};
+use std::ffi::OsString;
+use std::io::{self, Write};
 
 pub fn build_cli(cli_config: &CliConfig) -> Command {
-    // Pass a string slice directly to the Command constructor
-    let mut app = Command::new(cli_config.app_name)
-        .version(cli_config.version)
-        .about(cli_config.about);
+    // Clap takes `Cow` names as they are: borrowed ones stay borrowed, owned
+    // ones are cloned
+    let theme = cli_config.theme.clone().unwrap_or_else(ui::theme);
+    let mut app = Command::new(cli_config.app_name.clone())
+        .version(cli_config.version.clone())
+        .about(cli_config.about.clone())
+        .styles(help::styles(&theme))
+        .color(clap_color(ui::color_choice()));
 
     for cmd in cli_config.commands.iter() {
-        let mut command = Command::new(cmd.name).about(cmd.description);
+        let mut command = Command::new(cmd.name.clone())
+            .about(cmd.description.clone())
+            .visible_aliases(cmd.aliases.clone())
+            .hide(cmd.hidden);
+        if let Some(replacement) = &cmd.deprecated {
+            command = command.about(format!(
+                "{} (deprecated, use '{}')",
+                cmd.description, replacement
+            ));
+        }
+        if let Some(long_description) = &cmd.long_description {
+            command = command.long_about(long_description.clone());
+        }
+        if let Some(after_help) = help::after_help(cmd) {
+            command = command.after_long_help(after_help);
+        }
 
         for arg in cmd.args.iter() {
-            let argument = Arg::new(&*arg.name).help(&arg.help).required(arg.required);
-            command = command.arg(argument);
+            command = command.arg(build_arg(cli_config, Some(&cmd.name), arg));
+        }
+        for group in cmd.groups.iter() {
+            command = command.group(
+                ArgGroup::new(group.name.clone())
+                    .args(group.args.clone())
+                    .required(group.required)
+                    .multiple(group.multiple),
+            );
         }
 
         app = app.subcommand(command);
     }
 
-    app
+    // Global args are accepted before or after the subcommand
+    for arg in cli_config.global_args.iter() {
+        app = app.arg(build_arg(cli_config, None, arg).global(true));
+    }
+    for arg in context::standard_global_args() {
+        if !has_arg(cli_config, &arg) {
+            app = app.arg(arg);
+        }
+    }
+
+    if cli_config.settings.is_some() && !has_command(cli_config, "config") {
+        app = app.subcommand(settings::config_command());
+    }
+    if !has_command(cli_config, "completions") {
+        app = app.subcommand(completions::completions_command());
+    }
+    if cli_config.docs_command && !has_command(cli_config, "generate-docs") {
+        app = app.subcommand(docs::docs_command());
+    }
+
+    help::apply_command_groups(cli_config, app)
+}
+
+fn build_arg(cli_config: &CliConfig, command: Option<&str>, arg: &ArgConfig) -> Arg {
+    let mut argument = Arg::new(arg.name.clone())
+        .help(&arg.help)
+        .required(arg.required)
+        .hide(arg.hidden);
+    if arg.flag {
+        argument = argument.action(ArgAction::SetTrue);
+    }
+    if !arg.conflicts_with.is_empty() {
+        argument = argument.conflicts_with_all(arg.conflicts_with.clone());
+    }
+    for other in arg.requires.iter() {
+        argument = argument.requires(other.clone());
+    }
+    if !arg.required_unless_present.is_empty() {
+        argument = argument.required_unless_present_any(arg.required_unless_present.clone());
+    }
+    if let Some(long) = &arg.long {
+        argument = argument.long(long.clone());
+    }
+    if let Some(heading) = &arg.help_heading {
+        argument = argument.help_heading(heading.clone());
+    }
+    if let Some(short) = arg.short {
+        argument = argument.short(short);
+    }
+    #[cfg(feature = "dynamic-completions")]
+    if let Some(completer) = &arg.completer {
+        argument = argument.add(completions::value_completer(completer));
+    }
+    // clap resolves the value as command line > env var > default
+    if let Some(env) = cli_config.env_var_name(arg) {
+        argument = argument.env(env).hide_env_values(!arg.show_env_value);
+    }
+    let config_default = cli_config
+        .settings
+        .as_ref()
+        .and_then(|settings| match command {
+            Some(command) => settings.arg_default(command, &arg.name),
+            None => settings.get(&arg.name),
+        });
+    if let Some(default) = config_default.or(arg.default_value.as_deref()) {
+        // A default satisfies a required arg
+        argument = argument.default_value(default.to_string()).required(false);
+    }
+    argument
+}
+
+fn has_command(cli_config: &CliConfig, name: &str) -> bool {
+    cli_config.commands.iter().any(|cmd| cmd.name == name)
+}
+
+fn clap_color(choice: ColorChoice) -> clap::ColorChoice {
+    match choice {
+        ColorChoice::Auto => clap::ColorChoice::Auto,
+        ColorChoice::Always => clap::ColorChoice::Always,
+        ColorChoice::Never => clap::ColorChoice::Never,
+    }
+}
+
+// The value of the built-in `--color` in `args`, the last one if repeated
+fn color_arg(cli_config: &CliConfig, args: &[OsString]) -> Option<ColorChoice> {
+    let builtin = context::standard_global_args()
+        .into_iter()
+        .find(|arg| arg.get_id() == "color")?;
+    if has_arg(cli_config, &builtin) {
+        return None;
+    }
+    let mut choice = None;
+    let mut args = args
+        .iter()
+        .skip(1)
+        .map(|arg| arg.to_string_lossy().into_owned());
+    while let Some(arg) = args.next() {
+        let value = match arg.as_str() {
+            "--" => break,
+            "--color" => args.next(),
+            _ => match arg.strip_prefix("--color=") {
+                Some(value) => Some(value.to_string()),
+                None => continue,
+            },
+        };
+        choice = match value.as_deref() {
+            Some("auto") => Some(ColorChoice::Auto),
+            Some("always") => Some(ColorChoice::Always),
+            Some("never") => Some(ColorChoice::Never),
+            _ => choice,
+        };
+    }
+    choice
+}
+
+// Whether a user arg already uses the name, long or short of a built-in one
+fn has_arg(cli_config: &CliConfig, builtin: &Arg) -> bool {
+    let mut user_args = cli_config
+        .global_args
+        .iter()
+        .chain(cli_config.commands.iter().flat_map(|cmd| cmd.args.iter()));
+    user_args.any(|arg| {
+        arg.name == builtin.get_id().as_str()
+            || (arg.long.is_some() && arg.long.as_deref() == builtin.get_long())
+            || (arg.short.is_some() && arg.short == builtin.get_short())
+    })
+}
+
+// Values of the given args, leaving out optional ones that were not supplied
+fn collect_args<'c>(
+    arg_configs: impl Iterator<Item = &'c ArgConfig>,
+    matches: &ArgMatches,
+) -> Args {
+    let mut args = Args::new();
+    for arg in arg_configs {
+        if arg.flag {
+            if matches.get_flag(&arg.name) {
+                args.insert(arg.name.clone(), "true");
+            }
+        } else if let Some(value) = matches.get_one::<String>(&arg.name) {
+            args.insert(arg.name.clone(), value.as_str());
+        }
+    }
+    args
+}
+
+/// What a run of the app did, as reported by `run_from`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Outcome {
+    pub exit_code: i32,
+    pub stdout: String,
+    pub stderr: String,
+    // What a UIWithReturn action returned
+    pub value: Option<String>,
+}
+
+impl Outcome {
+    pub fn success(&self) -> bool {
+        self.exit_code == 0
+    }
 }
 
 pub fn execute_cli(cli_config: &CliConfig) {
-    let matches = build_cli(cli_config).get_matches();
-    if let Some((command_name, sub_matches)) = matches.subcommand() {
-        if let Some(cmd) = cli_config
-            .commands()
-            .iter()
-            .find(|cmd| cmd.name == command_name)
-        {
-            let args: Vec<String> = cmd
-                .args
-                .iter()
-                .map(|arg| sub_matches.get_one::<String>(arg.name).unwrap().to_string())
-                .collect();
-            match &cmd.command_type {
-                CommandType::Standard(_) => {
-                    if args.is_empty() {
-                        cmd.execute_action(None);
-                    }
-                    cmd.execute_action(Some(&args));
-                }
-                CommandType::UI(_) => {
-                    if args.is_empty() {
-                        cmd.execute_action(None);
-                    }
-                    cmd.execute_action(Some(&args));
-                }
-                CommandType::UIWithReturn(_) => {
-                    if args.is_empty() {
-                        cmd.execute_action(None);
-                    }
-                    cmd.execute_action(Some(&args));
+    completions::complete_if_requested(cli_config);
+    signals::install_handler();
+
+    let (exit_code, _) = run_args(cli_config, std::env::args_os(), &Io::std());
+    if exit_code != 0 {
+        std::process::exit(exit_code);
+    }
+}
+
+/// Runs the app on the given command line and streams, the way `execute_cli`
+/// does with those of the process, and reports what happened instead of
+/// exiting.
+///
+/// The first item of `args` is the program name. Prompts don't read `io`,
+/// call this inside `testing::with_keys` to script them.
+pub fn run_from<I, T>(cli_config: &CliConfig, args: I, io: Io) -> Outcome
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let (io, stdout, stderr) = io.capture();
+    let (exit_code, value) = run_args(cli_config, args, &io);
+    let text = |output: Captured| String::from_utf8_lossy(&output.lock().unwrap()).into_owned();
+    Outcome {
+        exit_code,
+        stdout: text(stdout),
+        stderr: text(stderr),
+        value,
+    }
+}
+
+// Returns the exit code and the value returned by the action
+fn run_args<I, T>(cli_config: &CliConfig, args: I, io: &Io) -> (i32, Option<String>)
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let _scope = RunScope::enter(cli_config);
+    let args: Vec<OsString> = args.into_iter().map(Into::into).collect();
+    let bin = completions::bin_name(cli_config, args.first().map(OsString::as_os_str));
+    // Parse errors come before the flags, so look for `--json` by hand
+    let json = args.iter().skip(1).any(|arg| arg == "--json");
+    // And for `--color`, which clap needs for help and errors
+    if let Some(color) = color_arg(cli_config, &args) {
+        ui::replace_local_color_choice(Some(color));
+    }
+    // Mistyped commands fail here with a "did you mean" tip from clap
+    match build_cli(cli_config).try_get_matches_from(&args) {
+        Ok(matches) if matches.subcommand().is_none() => {
+            run_bare_invocation(cli_config, &bin, &args, &matches, io)
+        }
+        Ok(matches) => run_matches(cli_config, &bin, &matches, io),
+        Err(err) if json && err.use_stderr() => {
+            let rendered = err.render().to_string();
+            let message = rendered.lines().next().unwrap_or_default();
+            let message = message.trim_start_matches("error: ");
+            let report = json_error(None, message, err.exit_code());
+            let _ = writeln!(io.stdout(), "{}", report);
+            (err.exit_code(), None)
+        }
+        Err(err) => (report_clap_error(&err, io), None),
+    }
+}
+
+// Installs the backend and theme of the app for this thread only, and puts
+// back what the thread had, flags and cancellation token included, once the
+// run is over
+struct RunScope {
+    backend: Option<ui::SharedBackend>,
+    theme: Option<ui::Theme>,
+    flags: Option<GlobalFlags>,
+    color: Option<ColorChoice>,
+    token: Option<signals::CancellationToken>,
+}
+
+impl RunScope {
+    fn enter(cli_config: &CliConfig) -> Self {
+        let backend = ui::replace_local_backend(None);
+        // A backend from `testing::with_keys` wins over the one of the app
+        ui::replace_local_backend(backend.clone().or_else(|| cli_config.backend.clone()));
+        let theme = ui::replace_local_theme(None);
+        ui::replace_local_theme(cli_config.theme.clone().or_else(|| theme.clone()));
+        RunScope {
+            backend,
+            theme,
+            flags: wrappers::replace_local_flags(None),
+            color: ui::replace_local_color_choice(None),
+            token: signals::replace_local_token(None),
+        }
+    }
+}
+
+impl Drop for RunScope {
+    fn drop(&mut self) {
+        ui::replace_local_backend(self.backend.take());
+        ui::replace_local_theme(self.theme.take());
+        wrappers::replace_local_flags(self.flags.take());
+        ui::replace_local_color_choice(self.color.take());
+        if let Some(token) = signals::replace_local_token(self.token.take()) {
+            signals::end_run(&token);
+        }
+    }
+}
+
+// Help and version requests end up here too, and go to stdout
+fn report_clap_error(err: &clap::Error, io: &Io) -> i32 {
+    if io.is_std() {
+        // Lets clap pick colors for the terminal
+        let _ = err.print();
+    } else if err.use_stderr() {
+        let _ = write!(io.stderr(), "{}", err.render());
+    } else {
+        let _ = write!(io.stdout(), "{}", err.render());
+    }
+    err.exit_code()
+}
+
+fn print_help(cli_config: &CliConfig, io: &Io) {
+    if io.is_std() {
+        let _ = build_cli(cli_config).print_help();
+    } else {
+        let _ = write!(io.stdout(), "{}", build_cli(cli_config).render_help());
+    }
+}
+
+// `bin` is the name the program was run by
+fn run_matches(
+    cli_config: &CliConfig,
+    bin: &str,
+    matches: &ArgMatches,
+    io: &Io,
+) -> (i32, Option<String>) {
+    // Runs without a command go to `run_bare_invocation` instead
+    let Some((command_name, sub_matches)) = matches.subcommand() else {
+        return (0, None);
+    };
+    if command_name == "completions" && !has_command(cli_config, "completions") {
+        let exit_code = completions::run_completions_command(cli_config, bin, sub_matches, io);
+        return (exit_code, None);
+    }
+    if command_name == "generate-docs" && cli_config.docs_command {
+        let exit_code = docs::run_docs_command(cli_config, sub_matches, io);
+        return (exit_code, None);
+    }
+    if let (Some(settings), false) = (&cli_config.settings, has_command(cli_config, "config")) {
+        if command_name == "config" {
+            let exit_code =
+                settings::run_config_command(&cli_config.app_name, settings, sub_matches, io);
+            return (exit_code, None);
+        }
+    }
+
+    let Some(cmd) = cli_config
+        .commands()
+        .iter()
+        .find(|cmd| cmd.name == command_name)
+    else {
+        let _ = writeln!(io.stderr(), "Command '{}' not recognized.", command_name);
+        return (1, None);
+    };
+    let args = collect_args(
+        cli_config.global_args.iter().chain(cmd.args.iter()),
+        sub_matches,
+    );
+    let flags = GlobalFlags::from_matches(sub_matches);
+    wrappers::set_local_flags(flags.clone());
+    if let Some(warning) = cmd.deprecation_warning() {
+        let _ = writeln!(io.stderr(), "{}", warning);
+    }
+    let ctx = Context {
+        command: cmd.name.to_string(),
+        args,
+        flags,
+        terminal: io.terminal(),
+        settings: cli_config.settings.clone(),
+        extensions: cli_config.extensions.clone(),
+        cancel: signals::start_run(),
+        io: io.clone(),
+        returned: Default::default(),
+    };
+    let result = match hooks::run_command(cli_config, cmd, &ctx) {
+        // An action may stop early without returning `cancelled()`
+        Ok(()) if ctx.cancel.is_cancelled() => Err(signals::cancelled()),
+        result => result,
+    };
+    let value = ctx.returned.lock().unwrap().take();
+    let exit_code = match &result {
+        Err(err) if signals::is_cancelled(err) => signals::CANCELLED_EXIT_CODE,
+        Err(_) => 1,
+        Ok(()) => 0,
+    };
+    if ctx.flags.output == OutputFormat::Json {
+        let report = match &result {
+            Ok(()) => json!({
+                "command": ctx.command,
+                "ok": true,
+                "result": value,
+            }),
+            Err(err) => json_error(Some(&ctx.command), &err.to_string(), exit_code),
+        };
+        let _ = writeln!(io.stdout(), "{}", report);
+    } else if let Err(err) = &result {
+        if exit_code == signals::CANCELLED_EXIT_CODE {
+            let _ = writeln!(io.stderr(), "Cancelled.");
+        } else {
+            let _ = writeln!(io.stderr(), "Error: {}", err);
+        }
+    }
+    // Text as the action gave it, or the JSON of structured values
+    let value = value.map(|value| match value {
+        Value::String(text) => text,
+        value => value.to_string(),
+    });
+    (exit_code, value)
+}
+
+// What `--json` prints for a command that failed or was cancelled
+fn json_error(command: Option<&str>, message: &str, exit_code: i32) -> Value {
+    json!({
+        "command": command,
+        "ok": false,
+        "error": message,
+        "exit_code": exit_code,
+    })
+}
+
+// `args` is the command line, which only holds global args when there is no
+// command, so they carry over to the command that is run
+fn run_bare_invocation(
+    cli_config: &CliConfig,
+    bin: &str,
+    args: &[OsString],
+    matches: &ArgMatches,
+    io: &Io,
+) -> (i32, Option<String>) {
+    let flags = GlobalFlags::from_matches(matches);
+    let command = match &cli_config.bare_invocation {
+        BareInvocation::Command(name) => vec![name.clone()],
+        BareInvocation::Picker if !flags.no_input => match pick_command(cli_config) {
+            Ok(command) => command,
+            Err(err) => {
+                let exit_code = if signals::is_cancelled(&err) {
+                    signals::CANCELLED_EXIT_CODE
+                } else {
+                    1
+                };
+                if flags.output == OutputFormat::Json {
+                    let report = json_error(None, &err.to_string(), exit_code);
+                    let _ = writeln!(io.stdout(), "{}", report);
+                } else if exit_code == signals::CANCELLED_EXIT_CODE {
+                    let _ = writeln!(io.stderr(), "Cancelled.");
+                } else {
+                    let _ = writeln!(io.stderr(), "Error: {}", err);
                 }
+                return (exit_code, None);
             }
-        } else {
-            eprintln!("Command '{}' not recognized.", command_name);
+        },
+        _ => {
+            print_help(cli_config, io);
+            return (0, None);
         }
-    } else {
-        eprintln!("No subcommand was used.");
+    };
+    let argv = args
+        .iter()
+        .cloned()
+        .chain(command.into_iter().map(Into::into));
+    match build_cli(cli_config).try_get_matches_from(argv) {
+        Ok(matches) if matches.subcommand().is_some() => run_matches(cli_config, bin, &matches, io),
+        Ok(_) => (0, None),
+        Err(err) => (report_clap_error(&err, io), None),
+    }
+}
+
+// The name of the command picked from a menu and the args it can't run
+// without, failing with `cancelled()` when the user gives up
+fn pick_command(cli_config: &CliConfig) -> io::Result<Vec<String>> {
+    let commands: Vec<_> = cli_config
+        .commands()
+        .iter()
+        .filter(|cmd| !cmd.hidden && cmd.deprecated.is_none())
+        .collect();
+    let width = commands
+        .iter()
+        .map(|cmd| cmd.name.len())
+        .max()
+        .unwrap_or_default();
+    let entries: Vec<String> = commands
+        .iter()
+        .map(|cmd| format!("{:width$}  {}", cmd.name, cmd.description))
+        .collect();
+
+    let selected = ui::prompt(&Prompt::Menu {
+        title: &cli_config.app_name,
+        subtitle: "Pick a command",
+        options: &entries,
+    })?
+    .ok_or_else(signals::cancelled)?;
+    let cmd = entries
+        .iter()
+        .position(|entry| *entry == selected)
+        .map(|index| commands[index])
+        .ok_or_else(|| io::Error::other(format!("no command for '{}'", selected)))?;
+
+    let mut argv = vec![cmd.name.to_string()];
+    let mut given: Vec<&str> = Vec::new();
+    for arg in missing_required_args(cli_config, cmd) {
+        // Asked for an arg this one is required unless present
+        if arg
+            .required_unless_present
+            .iter()
+            .any(|other| given.contains(&other.as_str()))
+        {
+            continue;
+        }
+        ask_arg(arg, &mut argv)?;
+        given.push(&arg.name);
+    }
+    for group in missing_required_groups(cli_config, cmd) {
+        if group.iter().any(|arg| given.contains(&arg.name.as_ref())) {
+            continue;
+        }
+        let arg = match group.as_slice() {
+            [arg] => *arg,
+            _ => pick_group_arg(&group)?,
+        };
+        ask_arg(arg, &mut argv)?;
+        given.push(&arg.name);
+    }
+    Ok(argv)
+}
+
+// Lets the user choose which arg of a required group to give
+fn pick_group_arg<'c>(group: &[&'c ArgConfig]) -> io::Result<&'c ArgConfig> {
+    let names: Vec<String> = group.iter().map(|arg| arg.name.to_string()).collect();
+    let selected = ui::prompt(&Prompt::Menu {
+        title: "One of these is required",
+        subtitle: "Pick the one to give",
+        options: &names,
+    })?
+    .ok_or_else(signals::cancelled)?;
+    group
+        .iter()
+        .find(|arg| arg.name == selected.as_str())
+        .copied()
+        .ok_or_else(|| io::Error::other(format!("no arg named '{}'", selected)))
+}
+
+// Appends `arg` to `argv`, with a value asked for unless it is a flag
+fn ask_arg(arg: &ArgConfig, argv: &mut Vec<String>) -> io::Result<()> {
+    if let Some(long) = &arg.long {
+        argv.push(format!("--{}", long));
+    } else if let Some(short) = arg.short {
+        argv.push(format!("-{}", short));
     }
+    if arg.flag {
+        return Ok(());
+    }
+    let label = if arg.help.is_empty() {
+        arg.name.as_ref()
+    } else {
+        arg.help.as_str()
+    };
+    let value = wrappers::input_form(&arg.name, "", label)?.ok_or_else(signals::cancelled)?;
+    argv.push(value);
+    Ok(())
+}
+
+// Whether a config file, env var or default gives `arg` a value
+fn has_fallback(cli_config: &CliConfig, cmd: &CommandConfig, arg: &ArgConfig) -> bool {
+    arg.default_value.is_some()
+        || cli_config
+            .settings
+            .as_ref()
+            .and_then(|settings| settings.arg_default(&cmd.name, &arg.name))
+            .is_some()
+        || cli_config
+            .env_var_name(arg)
+            .is_some_and(|env| std::env::var_os(env).is_some())
+}
+
+// Required args that no env var, config file or default would fill in,
+// including those required unless other args are present when none of the
+// others would be filled in either
+fn missing_required_args<'c>(cli_config: &CliConfig, cmd: &'c CommandConfig) -> Vec<&'c ArgConfig> {
+    let filled = |name: &String| {
+        cmd.args
+            .iter()
+            .find(|arg| arg.name == name.as_str())
+            .is_some_and(|arg| has_fallback(cli_config, cmd, arg))
+    };
+    cmd.args
+        .iter()
+        .filter(|arg| arg.required || !arg.required_unless_present.is_empty())
+        .filter(|arg| !has_fallback(cli_config, cmd, arg))
+        .filter(|arg| !arg.required_unless_present.iter().any(filled))
+        .collect()
+}
+
+// The args of each required group none of which would be filled in
+fn missing_required_groups<'c>(
+    cli_config: &CliConfig,
+    cmd: &'c CommandConfig,
+) -> Vec<Vec<&'c ArgConfig>> {
+    cmd.groups
+        .iter()
+        .filter(|group| group.required)
+        .map(|group| {
+            cmd.args
+                .iter()
+                .filter(|arg| group.args.iter().any(|name| arg.name == name.as_str()))
+                .collect::<Vec<_>>()
+        })
+        .filter(|args| !args.is_empty())
+        .filter(|args| !args.iter().any(|arg| has_fallback(cli_config, cmd, arg)))
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{ArgConfig, CliConfig, CommandConfig, CommandType};
-    use std::sync::Arc;
+    use crate::config::{ArgGroupConfig, CommandType};
+    use crate::context::Extensions;
+    use crate::hooks::Hooks;
+    use crate::settings::Settings;
+    use crate::testing::Keys;
+    use crate::wrappers::TextAreaOptions;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::{Arc, Mutex};
 
     fn get_test_cli_config() -> CliConfig<'static> {
         CliConfig {
-            app_name: "test_app",
-            version: "1.0",
-            about: "A test application",
+            app_name: "test_app".into(),
+            version: "1.0".into(),
+            about: "A test application".into(),
+            env_prefix: None,
+            settings: None,
+            docs_command: false,
+            bare_invocation: BareInvocation::Help,
+            hooks: Hooks::new(),
+            middleware: vec![],
+            extensions: Extensions::new(),
+            global_args: vec![],
+            runtime: None,
+            backend: None,
+            theme: None,
             commands: vec![CommandConfig {
-                name: "test_cmd",
-                description: "A test command",
+                name: "test_cmd".into(),
+                description: "A test command".into(),
+                groups: vec![],
                 args: vec![ArgConfig {
-                    name: "message",
+                    name: "message".into(),
                     help: "A test message".to_string(),
                     required: true,
+                    ..Default::default()
                 }],
                 command_type: CommandType::Standard(Arc::new(|args| {
                     println!("Executing test_cmd with args: {:?}", args);
                 })),
+                examples: vec![],
+                long_description: None,
+                aliases: vec![],
+                see_also: vec![],
+                group: None,
+                hidden: false,
+                deprecated: None,
+                hooks: Hooks::new(),
             }],
         }
     }
@@ -168,4 +763,688 @@ mod tests {
             assert!(matches.subcommand_matches("test_cmd").is_none());
         }
     }
+
+    // Tests run in parallel, so the ones setting environment variables take
+    // turns, and use names that no other test reads
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    fn with_env_var<T>(name: &str, value: &str, f: impl FnOnce() -> T) -> T {
+        let _lock = ENV_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        std::env::set_var(name, value);
+        let output = panic::catch_unwind(AssertUnwindSafe(f));
+        std::env::remove_var(name);
+        output.unwrap_or_else(|err| panic::resume_unwind(err))
+    }
+
+    #[test]
+    fn test_env_binding_precedence() {
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        cli_config.set_env_prefix("BUBBLERS_PRECEDENCE_");
+        let mut command = CommandConfig::new_standard("deploy", "Deploy", Arc::new(|_| {}));
+        command.add_arg(
+            ArgConfig::new("region", "Target region")
+                .long("region")
+                .env("REGION")
+                .default_value("us-east-1"),
+        );
+        cli_config.add_command(command);
+
+        let region = |argv: Vec<&str>| {
+            let matches = build_cli(&cli_config).try_get_matches_from(argv).unwrap();
+            let (_, sub_matches) = matches.subcommand().unwrap();
+            sub_matches.get_one::<String>("region").cloned()
+        };
+
+        assert_eq!(region(vec!["test_app", "deploy"]), Some("us-east-1".into()));
+
+        with_env_var("BUBBLERS_PRECEDENCE_REGION", "eu-west-1", || {
+            assert_eq!(region(vec!["test_app", "deploy"]), Some("eu-west-1".into()));
+            assert_eq!(
+                region(vec!["test_app", "deploy", "--region", "sa-east-1"]),
+                Some("sa-east-1".into())
+            );
+        });
+    }
+
+    #[test]
+    fn test_help_hides_env_values() {
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        cli_config.set_env_prefix("BUBBLERS_HELP_");
+        let mut command = CommandConfig::new_standard("deploy", "Deploy", Arc::new(|_| {}));
+        command
+            .add_arg(
+                ArgConfig::new("token", "API token")
+                    .long("token")
+                    .env("TOKEN"),
+            )
+            .add_arg(
+                ArgConfig::new("region", "Target region")
+                    .long("region")
+                    .env("REGION")
+                    .show_env_value(true),
+            );
+        cli_config.add_command(command);
+
+        let help = |name, value| {
+            with_env_var(name, value, || {
+                let mut app = build_cli(&cli_config);
+                let deploy = app.find_subcommand_mut("deploy").unwrap();
+                deploy.render_long_help().to_string()
+            })
+        };
+
+        let token_help = help("BUBBLERS_HELP_TOKEN", "s3cr3t");
+        assert!(token_help.contains("[env: BUBBLERS_HELP_TOKEN]"));
+        assert!(!token_help.contains("s3cr3t"));
+        let region_help = help("BUBBLERS_HELP_REGION", "eu-west-1");
+        assert!(region_help.contains("[env: BUBBLERS_HELP_REGION=eu-west-1]"));
+    }
+
+    #[test]
+    fn test_settings_feed_arg_defaults() {
+        let mut cli_config = get_test_cli_config();
+        let mut settings = Settings::new();
+        settings.set("test_cmd.message", "from config");
+        cli_config.set_settings(settings);
+
+        let matches = build_cli(&cli_config)
+            .try_get_matches_from(vec!["test_app", "test_cmd"])
+            .unwrap();
+        let (_, sub_matches) = matches.subcommand().unwrap();
+        assert_eq!(
+            sub_matches.get_one::<String>("message").map(String::as_str),
+            Some("from config")
+        );
+
+        let app = build_cli(&cli_config);
+        assert!(app.get_subcommands().any(|sc| sc.get_name() == "config"));
+
+        let get = |key| run_from(&cli_config, ["test_app", "config", "get", key], Io::null());
+        let outcome = get("test_cmd.message");
+        assert_eq!(
+            (outcome.exit_code, outcome.stdout.as_str()),
+            (0, "from config\n")
+        );
+        let outcome = get("nope");
+        assert_eq!(outcome.exit_code, 1);
+        assert_eq!(outcome.stderr, "Key 'nope' is not set.\n");
+    }
+
+    #[test]
+    fn test_build_cli_with_owned_names() {
+        let mut cli_config = CliConfig::new(String::from("scripts"), "1.0", "Generated commands");
+        for script in ["deploy.sh", "backup.sh"] {
+            let name = script.trim_end_matches(".sh").to_string();
+            let mut command = CommandConfig::new_standard(
+                name.clone(),
+                format!("Run {}", script),
+                Arc::new(|_| {}),
+            );
+            command.add_arg(ArgConfig::new(format!("{}-target", name), "Target host"));
+            cli_config.add_command(command);
+        }
+
+        let app = build_cli(&cli_config);
+        let deploy = app
+            .get_subcommands()
+            .find(|sc| sc.get_name() == "deploy")
+            .unwrap();
+        assert_eq!(
+            deploy.get_about().map(|s| s.to_string()),
+            Some("Run deploy.sh".to_string())
+        );
+        assert!(deploy
+            .get_arguments()
+            .any(|arg| arg.get_id() == "deploy-target"));
+    }
+
+    #[test]
+    fn test_hidden_and_deprecated_commands() {
+        let mut cli_config = get_test_cli_config();
+        let mut remove = CommandConfig::new_standard("remove", "Remove a file", Arc::new(|_| {}));
+        remove.add_alias("rm");
+        cli_config.add_command(remove);
+        let mut delete = CommandConfig::new_standard("delete", "Remove a file", Arc::new(|_| {}));
+        delete.deprecate("remove");
+        cli_config.add_command(delete);
+        let mut debug = CommandConfig::new_standard("debug", "Dump state", Arc::new(|_| {}));
+        debug.set_hidden(true);
+        cli_config.add_command(debug);
+
+        let help = build_cli(&cli_config).render_help().to_string();
+        assert!(help.contains("Remove a file [alias: rm]"));
+        assert!(help.contains("(deprecated, use 'remove')"));
+        assert!(!help.contains("debug"));
+
+        // Hidden commands and aliases still parse
+        for argv in [vec!["test_app", "rm"], vec!["test_app", "debug"]] {
+            assert!(build_cli(&cli_config).try_get_matches_from(argv).is_ok());
+        }
+        assert_eq!(
+            cli_config.commands()[2].deprecation_warning().as_deref(),
+            Some("warning: 'delete' is deprecated and will be removed, use 'remove' instead.")
+        );
+    }
+
+    #[test]
+    fn test_unknown_command_suggestion() {
+        let result =
+            build_cli(&get_test_cli_config()).try_get_matches_from(vec!["test_app", "test_cdm"]);
+
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("similar subcommand exists: 'test_cmd'"));
+    }
+
+    #[test]
+    fn test_missing_required_args() {
+        let mut cli_config = get_test_cli_config();
+        cli_config.set_env_prefix("BUBBLERS_PICKER_");
+        let cmd = &mut cli_config.commands[0];
+        cmd.add_arg(
+            ArgConfig::new("token", "API token")
+                .long("token")
+                .env("TOKEN")
+                .required(true),
+        )
+        .add_arg(
+            ArgConfig::new("region", "Region")
+                .long("region")
+                .required(true)
+                .default_value("eu"),
+        )
+        .add_arg(ArgConfig::new("verbose", "Verbose output").long("verbose"));
+
+        let names = |cli_config: &CliConfig| {
+            missing_required_args(cli_config, &cli_config.commands[0])
+                .iter()
+                .map(|arg| arg.name.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&cli_config), vec!["message", "token"]);
+
+        with_env_var("BUBBLERS_PICKER_TOKEN", "secret", || {
+            assert_eq!(names(&cli_config), vec!["message"]);
+        });
+    }
+
+    #[test]
+    fn test_missing_required_alternatives() {
+        let mut command = CommandConfig::new_standard("deploy", "Deploy", Arc::new(|_| {}));
+        command
+            .add_arg(ArgConfig::new("tag", "Image tag").long("tag"))
+            .add_arg(
+                ArgConfig::new("latest", "Latest image")
+                    .long("latest")
+                    .flag(),
+            )
+            .add_arg(
+                ArgConfig::new("region", "Region")
+                    .long("region")
+                    .required_unless_present("zone"),
+            )
+            .add_arg(
+                ArgConfig::new("zone", "Zone")
+                    .long("zone")
+                    .default_value("a"),
+            )
+            .add_arg(
+                ArgConfig::new("team", "Team")
+                    .long("team")
+                    .required_unless_present("owner"),
+            )
+            .add_arg(ArgConfig::new("owner", "Owner").long("owner"))
+            .add_group(ArgGroupConfig::exclusive("image", ["tag", "latest"]).required(true));
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        cli_config.add_command(command);
+        let cmd = &cli_config.commands[0];
+
+        let names = |args: Vec<&ArgConfig>| {
+            args.iter()
+                .map(|arg| arg.name.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(missing_required_args(&cli_config, cmd)), vec!["team"]);
+        let groups: Vec<_> = missing_required_groups(&cli_config, cmd)
+            .into_iter()
+            .map(names)
+            .collect();
+        assert_eq!(groups, vec![vec!["tag", "latest"]]);
+    }
+
+    #[test]
+    fn test_bare_invocation_keeps_global_args() {
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        let mut command = CommandConfig::new_structured("whoami", "Who am I", |ctx| {
+            Ok(json!({ "name": ctx.args.get("name"), "quiet": ctx.flags.quiet }))
+        });
+        command.add_arg(
+            ArgConfig::new("name", "Your name")
+                .long("name")
+                .required(true),
+        );
+        cli_config.add_command(command);
+        cli_config.add_command(CommandConfig::new_structured("status", "Status", |ctx| {
+            Ok(json!({ "quiet": ctx.flags.quiet }))
+        }));
+
+        cli_config.set_bare_invocation(BareInvocation::Picker);
+        let run = crate::testing::with_keys(Keys::new().enter().type_text("Ann").enter(), || {
+            run_from(&cli_config, ["test_app", "--json", "--quiet"], Io::null())
+        });
+        let report: Value = serde_json::from_str(&run.output.stdout).unwrap();
+        assert_eq!(report["result"], json!({ "name": "Ann", "quiet": true }));
+
+        let run = crate::testing::with_keys(Keys::new().ctrl('c'), || {
+            run_from(&cli_config, ["test_app"], Io::null())
+        });
+        assert_eq!(run.output.exit_code, signals::CANCELLED_EXIT_CODE);
+        assert_eq!(run.output.stderr, "Cancelled.\n");
+
+        cli_config.set_bare_invocation(BareInvocation::Command("status".into()));
+        let outcome = run_from(&cli_config, ["test_app", "--quiet", "--json"], Io::null());
+        let report: Value = serde_json::from_str(&outcome.stdout).unwrap();
+        assert_eq!(report["result"], json!({ "quiet": true }));
+    }
+
+    #[test]
+    fn test_global_args() {
+        let mut cli_config = get_test_cli_config();
+        cli_config.add_global_arg(ArgConfig::new("profile", "Profile to use").long("profile"));
+        cli_config.commands[0].add_arg(
+            ArgConfig::new("dry-run", "Only print")
+                .long("dry-run")
+                .flag(),
+        );
+
+        let matches = build_cli(&cli_config)
+            .try_get_matches_from(vec![
+                "test_app",
+                "--profile",
+                "prod",
+                "-vv",
+                "test_cmd",
+                "hi",
+                "--dry-run",
+                "--no-input",
+            ])
+            .unwrap();
+        let (_, sub_matches) = matches.subcommand().unwrap();
+
+        let args = collect_args(
+            cli_config
+                .global_args
+                .iter()
+                .chain(cli_config.commands[0].args.iter()),
+            sub_matches,
+        );
+        assert_eq!(args.get("profile"), Some("prod"));
+        assert_eq!(args.get("message"), Some("hi"));
+        assert_eq!(args.get("dry-run"), Some("true"));
+
+        let flags = GlobalFlags::from_matches(sub_matches);
+        assert_eq!(flags.verbose, 2);
+        assert!(flags.no_input);
+        assert!(!flags.quiet);
+    }
+
+    #[test]
+    fn test_user_args_replace_standard_globals() {
+        let mut cli_config = get_test_cli_config();
+        cli_config.commands[0].add_arg(ArgConfig::new("quiet", "Whisper").long("quiet"));
+
+        let app = build_cli(&cli_config);
+        assert!(!app.get_arguments().any(|arg| arg.get_id() == "quiet"));
+        assert!(app.get_arguments().any(|arg| arg.get_id() == "verbose"));
+        app.debug_assert();
+    }
+
+    #[test]
+    fn test_arg_rules() {
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        let mut import = CommandConfig::new_standard("import", "Import data", Arc::new(|_| {}));
+        import
+            .add_arg(ArgConfig::new("file", "Read from a file").long("file"))
+            .add_arg(
+                ArgConfig::new("stdin", "Read from stdin")
+                    .long("stdin")
+                    .flag(),
+            )
+            .add_arg(ArgConfig::new("format", "Input format").long("format"))
+            .add_arg(
+                ArgConfig::new("delimiter", "Field delimiter")
+                    .long("delimiter")
+                    .requires("format"),
+            )
+            .add_group(ArgGroupConfig::exclusive("input", ["file", "stdin"]).required(true));
+        cli_config.add_command(import);
+        let mut export = CommandConfig::new_standard("export", "Export data", Arc::new(|_| {}));
+        export
+            .add_arg(
+                ArgConfig::new("out", "Output file")
+                    .long("out")
+                    .required_unless_present("print"),
+            )
+            .add_arg(
+                ArgConfig::new("print", "Print instead")
+                    .long("print")
+                    .flag()
+                    .conflicts_with("out"),
+            )
+            .add_group(ArgGroupConfig::at_least_one("fields", ["name", "size"]))
+            .add_arg(ArgConfig::new("name", "Include names").long("name").flag())
+            .add_arg(ArgConfig::new("size", "Include sizes").long("size").flag());
+        cli_config.add_command(export);
+
+        let parse = |argv: &[&str]| {
+            let mut full = vec!["test_app"];
+            full.extend_from_slice(argv);
+            build_cli(&cli_config).try_get_matches_from(full)
+        };
+
+        assert!(parse(&["import", "--file", "a.csv"]).is_ok());
+        assert!(parse(&["import", "--stdin"]).is_ok());
+        assert!(parse(&["import"]).is_err());
+        assert!(parse(&["import", "--file", "a.csv", "--stdin"]).is_err());
+        assert!(parse(&["import", "--stdin", "--delimiter", ";"]).is_err());
+        assert!(parse(&["import", "--stdin", "--format", "csv", "--delimiter", ";"]).is_ok());
+
+        assert!(parse(&["export", "--out", "a.csv", "--name"]).is_ok());
+        assert!(parse(&["export", "--print", "--name", "--size"]).is_ok());
+        assert!(parse(&["export", "--name"]).is_err());
+        assert!(parse(&["export", "--print"]).is_err());
+        assert!(parse(&["export", "--print", "--out", "a.csv", "--name"]).is_err());
+    }
+
+    #[test]
+    fn test_run_from_captures_outcome() {
+        let mut cli_config = get_test_cli_config();
+        cli_config
+            .add_command(CommandConfig::new_ui_with_return(
+                "echo",
+                "Echo the first line of stdin",
+                Arc::new(|ctx| ctx.io.read_line()),
+            ))
+            .add_command(CommandConfig::new_ui(
+                "fail",
+                "Always fail",
+                Arc::new(|ctx| {
+                    writeln!(ctx.stdout(), "trying")?;
+                    Err(std::io::Error::other("no luck"))
+                }),
+            ))
+            .add_command(CommandConfig::new_ui_with_return(
+                "ask",
+                "Ask a name",
+                Arc::new(|ctx| ctx.prompt("Name", "")),
+            ))
+            .add_command(CommandConfig::new_ui(
+                "stop",
+                "Stop as if interrupted",
+                Arc::new(|ctx| {
+                    ctx.cancel.cancel();
+                    Ok(())
+                }),
+            ));
+
+        let outcome = run_from(
+            &cli_config,
+            ["test_app", "echo"],
+            Io::with_stdin("hi\nthere"),
+        );
+        assert!(outcome.success());
+        assert_eq!(outcome.value.as_deref(), Some("hi"));
+        assert_eq!(outcome.stdout, "hi\n");
+
+        let outcome = run_from(&cli_config, ["test_app", "fail"], Io::null());
+        assert_eq!(outcome.exit_code, 1);
+        assert_eq!(outcome.stdout, "trying\n");
+        assert_eq!(outcome.stderr, "Error: no luck\n");
+
+        let outcome = run_from(&cli_config, ["test_app", "stop"], Io::null());
+        assert_eq!(outcome.exit_code, signals::CANCELLED_EXIT_CODE);
+        assert_eq!(outcome.stderr, "Cancelled.\n");
+
+        let outcome = run_from(&cli_config, ["test_app", "test_cdm"], Io::null());
+        assert_eq!(outcome.exit_code, 2);
+        assert!(outcome.stderr.contains("similar subcommand exists"));
+
+        let outcome = run_from(&cli_config, ["test_app", "--help"], Io::null());
+        assert!(outcome.success());
+        assert!(outcome.stdout.contains("Usage: test_app"));
+
+        let run = crate::testing::with_keys(Keys::new().type_text("abc").enter(), || {
+            run_from(&cli_config, ["test_app", "ask"], Io::null())
+        });
+        assert_eq!(run.output.value.as_deref(), Some("abc"));
+    }
+
+    // A stdout that can't be written, like a closed pipe
+    struct BrokenPipe;
+
+    impl Write for BrokenPipe {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_builtin_command_failures_exit_non_zero() {
+        let cli_config = get_test_cli_config();
+        let broken = || Io::new(std::io::empty(), BrokenPipe, std::io::sink());
+
+        let outcome = run_from(&cli_config, ["test_app", "completions", "bash"], Io::null());
+        assert!(outcome.success());
+        assert!(outcome.stdout.contains("test_app"));
+
+        let outcome = run_from(&cli_config, ["test_app", "completions", "bash"], broken());
+        assert_eq!(outcome.exit_code, 1);
+        assert!(outcome.stderr.starts_with("Could not generate completions"));
+
+        let mut cli_config = get_test_cli_config();
+        cli_config.enable_docs_command();
+        let out_dir = std::env::temp_dir().join(format!("bubblers-docs-{}", std::process::id()));
+        let generate = |out_dir: &std::path::Path| {
+            let out_dir = out_dir.to_str().unwrap();
+            let args = [
+                "test_app",
+                "generate-docs",
+                "--format",
+                "md",
+                "--out-dir",
+                out_dir,
+            ];
+            run_from(&cli_config, args, Io::null())
+        };
+
+        let outcome = generate(&out_dir);
+        assert!(outcome.success());
+        assert!(outcome.stdout.trim_end().ends_with("test_app.md"));
+
+        // A file where the directory should go
+        let outcome = generate(&out_dir.join("test_app.md"));
+        std::fs::remove_dir_all(&out_dir).unwrap();
+        assert_eq!(outcome.exit_code, 1);
+        assert!(outcome.stderr.starts_with("Could not generate docs"));
+    }
+
+    #[test]
+    fn test_json_output() {
+        let mut cli_config = get_test_cli_config();
+        cli_config
+            .add_command(CommandConfig::new_ui_with_return(
+                "echo",
+                "Echo the first line of stdin",
+                Arc::new(|ctx| ctx.io.read_line()),
+            ))
+            .add_command(CommandConfig::new_structured("stats", "Count", |_| {
+                Ok(serde_json::json!({ "files": 3 }))
+            }))
+            .add_command(CommandConfig::new_ui(
+                "fail",
+                "Always fail",
+                Arc::new(|_| Err(std::io::Error::other("no luck"))),
+            ));
+        let run = |args: &[&str], io| {
+            let outcome = run_from(&cli_config, args, io);
+            let json: Value = serde_json::from_str(&outcome.stdout).unwrap();
+            (outcome, json)
+        };
+
+        let (outcome, json) = run(&["test_app", "echo", "--json"], Io::with_stdin("hi"));
+        assert_eq!(
+            json,
+            json!({ "command": "echo", "ok": true, "result": "hi" })
+        );
+        assert_eq!(outcome.value.as_deref(), Some("hi"));
+
+        let (outcome, json) = run(&["test_app", "--json", "stats"], Io::null());
+        assert_eq!(json["result"], json!({ "files": 3 }));
+        assert_eq!(outcome.value.as_deref(), Some(r#"{"files":3}"#));
+
+        let (outcome, json) = run(&["test_app", "fail", "--json"], Io::null());
+        assert_eq!(outcome.exit_code, 1);
+        assert_eq!(json["error"], "no luck");
+        assert_eq!(outcome.stderr, "");
+
+        let (outcome, json) = run(&["test_app", "nope", "--json"], Io::null());
+        assert_eq!(outcome.exit_code, 2);
+        assert_eq!(json["ok"], false);
+        assert_eq!(json["command"], Value::Null);
+
+        let outcome = run_from(&cli_config, ["test_app", "stats"], Io::null());
+        assert_eq!(outcome.stdout, "{\n  \"files\": 3\n}\n");
+    }
+
+    #[test]
+    fn test_text_area_returns_text() {
+        let mut cli_config = get_test_cli_config();
+        cli_config.add_text_area_with_options(
+            "describe",
+            "Describe the change",
+            5,
+            Arc::new(|_| "Description".to_string()),
+            TextAreaOptions {
+                initial_text: "Fix ".to_string(),
+                max_lines: Some(2),
+                ..Default::default()
+            },
+        );
+
+        let keys = Keys::new()
+            .type_text("typo")
+            .enter()
+            .type_text("Body")
+            .enter()
+            .type_text("!")
+            .tab();
+        // Three lines come back to be fixed
+        let run = crate::testing::with_keys(keys.backspace().backspace().tab(), || {
+            run_from(&cli_config, ["test_app", "describe"], Io::null())
+        });
+        assert_eq!(run.output.value.as_deref(), Some("Fix typo\nBody"));
+        assert!(run
+            .frames
+            .iter()
+            .any(|frame| frame.contains("more than 2 lines")));
+
+        let outcome = run_from(
+            &cli_config,
+            ["test_app", "describe", "--text", "a\nb\nc"],
+            Io::null(),
+        );
+        assert_eq!(outcome.exit_code, 1);
+        assert_eq!(outcome.stderr, "Error: text has more than 2 lines\n");
+
+        // A trailing newline starts a line in the text area
+        let outcome = run_from(
+            &cli_config,
+            ["test_app", "describe", "--text", "a\nb\n"],
+            Io::null(),
+        );
+        assert_eq!(outcome.exit_code, 1);
+    }
+
+    #[test]
+    fn test_runs_on_two_threads_keep_their_flags() {
+        let barrier = Arc::new(std::sync::Barrier::new(2));
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        let both_started = barrier.clone();
+        cli_config.add_command(CommandConfig::new_structured(
+            "check",
+            "Check",
+            move |ctx| {
+                if ctx.flags.quiet {
+                    ctx.cancel.cancel();
+                }
+                // Both runs set their flags and token before either reads them
+                both_started.wait();
+                Ok(format!(
+                    "quiet={} cancelled={}",
+                    wrappers::global_flags().quiet,
+                    signals::cancellation_token().is_cancelled()
+                ))
+            },
+        ));
+        let cli_config = Arc::new(cli_config);
+
+        let run = |args: &'static [&'static str]| {
+            let cli_config = cli_config.clone();
+            std::thread::spawn(move || run_from(&cli_config, args, Io::null()))
+        };
+        let quiet = run(&["test_app", "--quiet", "check"]);
+        let loud = run(&["test_app", "check"]);
+
+        let (quiet, loud) = (quiet.join().unwrap(), loud.join().unwrap());
+        assert_eq!(quiet.value.as_deref(), Some("quiet=true cancelled=true"));
+        assert_eq!(quiet.exit_code, signals::CANCELLED_EXIT_CODE);
+        assert_eq!(loud.value.as_deref(), Some("quiet=false cancelled=false"));
+        assert_eq!(loud.exit_code, 0);
+    }
+
+    #[test]
+    fn test_theme_lasts_for_the_run() {
+        let mut cli_config = get_test_cli_config();
+        cli_config.set_theme(ui::Theme {
+            prompt_prefix: "$".to_string(),
+            ..Default::default()
+        });
+        cli_config.add_input("name", "Ask a name", "Name", "", "Name");
+
+        let keys = Keys::new().type_text("abc").enter();
+        let run = crate::testing::with_keys(keys, || {
+            run_from(&cli_config, ["test_app", "name"], Io::null())
+        });
+        assert_eq!(run.output.value.as_deref(), Some("abc"));
+        assert!(run.frames[0].contains("$ Name"));
+        assert_eq!(
+            ui::theme().prompt_prefix,
+            ui::Theme::default().prompt_prefix
+        );
+    }
+
+    #[test]
+    fn test_color_flag_reaches_clap() {
+        let cli_config = get_test_cli_config();
+        let color = |line: &[&str]| {
+            let args: Vec<OsString> = line.iter().map(OsString::from).collect();
+            color_arg(&cli_config, &args)
+        };
+        assert_eq!(
+            color(&["test_app", "--color", "never", "--help"]),
+            Some(ColorChoice::Never)
+        );
+        assert_eq!(
+            color(&["test_app", "echo", "--color=always"]),
+            Some(ColorChoice::Always)
+        );
+        assert_eq!(color(&["test_app", "echo", "--", "--color=always"]), None);
+
+        let previous = ui::replace_local_color_choice(Some(ColorChoice::Never));
+        assert_eq!(build_cli(&cli_config).get_color(), clap::ColorChoice::Never);
+        ui::replace_local_color_choice(previous);
+    }
 }
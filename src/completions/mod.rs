@@ -0,0 +1,182 @@
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use clap_complete::aot::{Generator, Shell};
+#[cfg(feature = "dynamic-completions")]
+use clap_complete::{env::Shells, ArgValueCompleter, CompleteEnv, CompletionCandidate};
+
+use crate::cli_builder::build_cli;
+use crate::config::CliConfig;
+#[cfg(feature = "dynamic-completions")]
+use crate::config::Completer;
+use crate::context::Io;
+
+// Env var the shell scripts set when asking the binary for completions
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+const SHELLS: [&str; 5] = ["bash", "elvish", "fish", "powershell", "zsh"];
+
+pub fn completions_command() -> Command {
+    let long_about = if cfg!(feature = "dynamic-completions") {
+        "Print a shell completion script to load from the shell's startup file.\n\
+         By default the script asks the program for candidates, so runtime values complete too."
+    } else {
+        "Print a shell completion script to load from the shell's startup file."
+    };
+    Command::new("completions")
+        .about("Print a shell completion script")
+        .long_about(long_about)
+        .arg(
+            Arg::new("shell")
+                .required(true)
+                .value_parser(SHELLS)
+                .help("Shell to generate the script for"),
+        )
+        .arg(
+            Arg::new("static")
+                .long("static")
+                .action(ArgAction::SetTrue)
+                .help("Print a standalone script without runtime completions"),
+        )
+}
+
+// Handles the built-in `completions` subcommand, returning its exit code
+pub fn run_completions_command(
+    cli_config: &CliConfig,
+    bin: &str,
+    matches: &ArgMatches,
+    io: &Io,
+) -> i32 {
+    let shell = matches.get_one::<String>("shell").unwrap();
+    let static_script = matches.get_flag("static");
+    match write_completions(cli_config, bin, shell, static_script, &mut io.stdout()) {
+        Ok(()) => 0,
+        Err(err) => {
+            let _ = writeln!(io.stderr(), "Could not generate completions: {}", err);
+            1
+        }
+    }
+}
+
+/// Writes the completion script of a shell, completing the program `bin`.
+///
+/// With the `dynamic-completions` feature, the default script registers the
+/// program itself as the completer, which lets `ArgConfig::completer` hooks
+/// run when the user presses tab. A static script only knows the commands
+/// and flags at generation time, and is the only kind without the feature.
+pub fn write_completions(
+    cli_config: &CliConfig,
+    bin: &str,
+    shell: &str,
+    static_script: bool,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    if static_script || !cfg!(feature = "dynamic-completions") {
+        let shell = Shell::from_str(shell).map_err(io::Error::other)?;
+        // `generate` panics when `out` can't be written
+        let mut app = build_cli(cli_config).bin_name(bin);
+        app.build();
+        return shell.try_generate(&app, out);
+    }
+
+    #[cfg(feature = "dynamic-completions")]
+    {
+        let shells = Shells::builtins();
+        let completer = shells
+            .completer(shell)
+            .ok_or_else(|| io::Error::other(format!("Unknown shell '{}'", shell)))?;
+        let exe = std::env::current_exe()?;
+        completer.write_registration(COMPLETE_VAR, bin, bin, &exe.to_string_lossy(), out)?;
+    }
+    Ok(())
+}
+
+/// Answers the completion request of a shell script and exits, if the
+/// process was started by one. Must run before anything is printed.
+#[cfg(feature = "dynamic-completions")]
+pub fn complete_if_requested(cli_config: &CliConfig) {
+    let arg0 = std::env::args_os().next();
+    CompleteEnv::with_factory(|| build_cli(cli_config))
+        .var(COMPLETE_VAR)
+        .bin(bin_name(cli_config, arg0.as_deref()))
+        .complete();
+}
+
+// Static scripts never call back into the program
+#[cfg(not(feature = "dynamic-completions"))]
+pub fn complete_if_requested(_cli_config: &CliConfig) {}
+
+#[cfg(feature = "dynamic-completions")]
+pub(crate) fn value_completer(completer: &Completer) -> ArgValueCompleter {
+    let completer = completer.clone();
+    ArgValueCompleter::new(move |current: &OsStr| {
+        let current = current.to_string_lossy();
+        completer(&current)
+            .into_iter()
+            .filter(|candidate| candidate.starts_with(current.as_ref()))
+            .map(CompletionCandidate::new)
+            .collect::<Vec<_>>()
+    })
+}
+
+/// The name users type to run the program, which the scripts complete for:
+/// the file name of the first command-line arg, else the app name.
+pub(crate) fn bin_name(cli_config: &CliConfig, arg0: Option<&OsStr>) -> String {
+    arg0.and_then(|arg0| Path::new(arg0).file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| cli_config.app_name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CommandConfig;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_static_script_lists_commands() {
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        cli_config.add_command(CommandConfig::new_standard(
+            "deploy",
+            "Deploy",
+            Arc::new(|_| {}),
+        ));
+
+        let mut script = Vec::new();
+        write_completions(&cli_config, "test-app", "bash", true, &mut script).unwrap();
+        let script = String::from_utf8(script).unwrap();
+
+        assert!(script.contains("deploy"));
+        assert!(script.contains("completions"));
+        assert!(script.contains("-o default test-app"));
+    }
+
+    #[test]
+    fn test_bin_name_comes_from_the_given_args() {
+        let cli_config = CliConfig::new("Test App", "1.0", "A test application");
+        let bin = |arg0: &str| bin_name(&cli_config, Some(OsStr::new(arg0)));
+        assert_eq!(bin("/usr/local/bin/tapp"), "tapp");
+        assert_eq!(bin_name(&cli_config, None), "Test App");
+    }
+
+    #[cfg(feature = "dynamic-completions")]
+    #[test]
+    fn test_value_completer_filters_by_prefix() {
+        use crate::config::ArgConfig;
+
+        let arg = ArgConfig::new("env", "Environment").completer(Arc::new(|_| {
+            vec!["staging".to_string(), "production".to_string()]
+        }));
+
+        let candidates =
+            value_completer(arg.completer.as_ref().unwrap()).complete(OsStr::new("st"));
+        let values: Vec<_> = candidates
+            .iter()
+            .map(|c| c.get_value().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(values, vec!["staging".to_string()]);
+    }
+}
@@ -0,0 +1,226 @@
+use std::{borrow::Cow, ops::Deref, sync::Arc};
+
+/// Suggests values for an arg when the user presses tab, given the text typed
+/// so far. Candidates not starting with that text are dropped.
+pub type Completer = Arc<dyn Fn(&str) -> Vec<String> + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct ArgConfig {
+    pub name: Cow<'static, str>,
+    pub help: String,
+    pub required: bool,
+    // Makes this a `--long` flag instead of a positional arg
+    pub long: Option<Cow<'static, str>>,
+    pub short: Option<char>,
+    // Env var read when the arg is not given, prefixed by `CliConfig::env_prefix`
+    pub env: Option<Cow<'static, str>>,
+    // Lists the current value of the env var in `--help`, which stays off so
+    // that secrets like tokens don't end up on screen
+    pub show_env_value: bool,
+    pub default_value: Option<Cow<'static, str>>,
+    pub completer: Option<Completer>,
+    // Section of the command help the arg is listed under
+    pub help_heading: Option<String>,
+    // Still accepted, but left out of help and docs
+    pub hidden: bool,
+    // A `--long` switch without a value, given to actions as "true" when set
+    pub flag: bool,
+    // Names of args that can't be given together with this one
+    pub conflicts_with: Vec<String>,
+    // Names of args that must be given whenever this one is
+    pub requires: Vec<String>,
+    // Makes the arg required unless one of these is given
+    pub required_unless_present: Vec<String>,
+}
+
+impl ArgConfig {
+    pub fn new(name: impl Into<Cow<'static, str>>, help: impl Into<String>) -> Self {
+        ArgConfig {
+            name: name.into(),
+            help: help.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    pub fn long(mut self, long: impl Into<Cow<'static, str>>) -> Self {
+        self.long = Some(long.into());
+        self
+    }
+
+    pub fn short(mut self, short: char) -> Self {
+        self.short = Some(short);
+        self
+    }
+
+    pub fn env(mut self, env: impl Into<Cow<'static, str>>) -> Self {
+        self.env = Some(env.into());
+        self
+    }
+
+    pub fn show_env_value(mut self, show: bool) -> Self {
+        self.show_env_value = show;
+        self
+    }
+
+    pub fn default_value(mut self, value: impl Into<Cow<'static, str>>) -> Self {
+        self.default_value = Some(value.into());
+        self
+    }
+
+    pub fn completer(mut self, completer: Completer) -> Self {
+        self.completer = Some(completer);
+        self
+    }
+
+    pub fn help_heading(mut self, heading: impl Into<String>) -> Self {
+        self.help_heading = Some(heading.into());
+        self
+    }
+
+    pub fn hidden(mut self, hidden: bool) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    pub fn flag(mut self) -> Self {
+        self.flag = true;
+        self
+    }
+
+    pub fn conflicts_with(mut self, arg: impl Into<String>) -> Self {
+        self.conflicts_with.push(arg.into());
+        self
+    }
+
+    pub fn requires(mut self, arg: impl Into<String>) -> Self {
+        self.requires.push(arg.into());
+        self
+    }
+
+    pub fn required_unless_present(mut self, arg: impl Into<String>) -> Self {
+        self.required_unless_present.push(arg.into());
+        self
+    }
+}
+
+/// A set of a command's args with a rule on how many of them may be given.
+///
+/// Maps onto clap's `ArgGroup`: `required` asks for at least one of the args,
+/// and without `multiple` they are mutually exclusive.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ArgGroupConfig {
+    pub name: String,
+    pub args: Vec<String>,
+    pub required: bool,
+    pub multiple: bool,
+}
+
+impl ArgGroupConfig {
+    pub fn new(name: impl Into<String>) -> Self {
+        ArgGroupConfig {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// At most one of `args` may be given.
+    pub fn exclusive<S: Into<String>>(
+        name: impl Into<String>,
+        args: impl IntoIterator<Item = S>,
+    ) -> Self {
+        ArgGroupConfig::new(name).args(args)
+    }
+
+    /// At least one of `args` must be given.
+    pub fn at_least_one<S: Into<String>>(
+        name: impl Into<String>,
+        args: impl IntoIterator<Item = S>,
+    ) -> Self {
+        ArgGroupConfig::new(name)
+            .args(args)
+            .required(true)
+            .multiple(true)
+    }
+
+    pub fn arg(mut self, arg: impl Into<String>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<S: Into<String>>(mut self, args: impl IntoIterator<Item = S>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn required(mut self, required: bool) -> Self {
+        self.required = required;
+        self
+    }
+
+    pub fn multiple(mut self, multiple: bool) -> Self {
+        self.multiple = multiple;
+        self
+    }
+}
+
+/// Values parsed for a command's args.
+///
+/// Derefs to the supplied values in declaration order, so optional args that
+/// were not given are skipped. Use `get` to look a value up by arg name.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Args {
+    names: Vec<String>,
+    values: Vec<String>,
+}
+
+impl Args {
+    pub fn new() -> Self {
+        Args::default()
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.names.push(name.into());
+        self.values.push(value.into());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.names
+            .iter()
+            .position(|n| *n == name)
+            .map(|i| self.values[i].as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.iter().any(|n| n == name)
+    }
+}
+
+impl Deref for Args {
+    type Target = [String];
+
+    fn deref(&self) -> &[String] {
+        &self.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_lookup_and_order() {
+        let mut args = Args::new();
+        args.insert("first", "a");
+        args.insert("third", "c");
+
+        assert_eq!(args.get("first"), Some("a"));
+        assert_eq!(args.get("second"), None);
+        assert!(args.contains("third"));
+        assert_eq!(&args[..], ["a".to_string(), "c".to_string()]);
+    }
+}
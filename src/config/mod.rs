@@ -1,73 +1,155 @@
-use std::{io, sync::Arc};
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::{any::Any, borrow::Cow, future::Future, time::Duration};
 
-use crossterm::style::Color;
 use rustubble::list::Item;
+use serde::Serialize;
+use serde_json::Value;
 
+mod args;
+
+pub use args::{ArgConfig, ArgGroupConfig, Args, Completer};
+
+use crate::context::{Context, Extensions, OutputFormat};
+use crate::hooks::{ErrorHook, FinallyHook, Hook, Hooks, Middleware};
+use crate::runtime::{default_runtime, AsyncAction, Runtime};
+use crate::settings::Settings;
+use crate::ui::{self, Backend, SharedBackend, Theme};
 use crate::wrappers::{
-    input_form, item_list, loader, menu_list, stopwatch, table, text_area, timed_progress, timer,
-    viewport,
+    input_form, item_list, loader, menu_list, open_editor, stopwatch, table, text_area_with,
+    timed_progress, timer, viewport, TextAreaOptions,
 };
 
-#[derive(Clone)]
-pub struct ArgConfig {
-    pub name: &'static str,
-    pub help: String,
-    pub required: bool,
-}
+pub type StandardAction<'a> = Arc<dyn Fn(&Context) + Send + Sync + 'a>;
+pub type UIAction<'a> = Arc<dyn Fn(&Context) -> Result<(), io::Error> + Send + Sync + 'a>;
+pub type UIWithReturnAction<'a> =
+    Arc<dyn Fn(&Context) -> Result<Option<String>, io::Error> + Send + Sync + 'a>;
+pub type StructuredAction<'a> = Arc<dyn Fn(&Context) -> io::Result<Value> + Send + Sync + 'a>;
+
+/// Supplies data to a UI helper when its command runs, instead of at
+/// configuration time. Receives the context of the command.
+pub type Provider<'a, T> = Arc<dyn Fn(&Context) -> T + Send + Sync + 'a>;
 
 #[derive(Clone)]
 pub enum CommandType<'a> {
-    Standard(Arc<dyn Fn(&[String]) + Send + Sync + 'a>),
-    UI(Arc<dyn Fn() -> Result<(), io::Error> + Send + Sync + 'a>),
-    UIWithReturn(Arc<dyn Fn() -> Result<Option<String>, io::Error> + Send + Sync + 'a>),
+    Standard(StandardAction<'a>),
+    UI(UIAction<'a>),
+    UIWithReturn(UIWithReturnAction<'a>),
+    Async(AsyncAction<'a>),
+    // Returns a value for scripts, see `CommandConfig::new_structured`
+    Structured(StructuredAction<'a>),
+}
+
+/// Headers and rows rendered by a table command.
+#[derive(Clone, Debug, Default)]
+pub struct TableData {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
 }
 
 #[derive(Clone)]
 pub struct CommandConfig<'a> {
-    pub name: &'static str,
-    pub description: &'static str,
+    pub name: Cow<'static, str>,
+    pub description: Cow<'static, str>,
     pub args: Vec<ArgConfig>,
+    // Rules across several args, like "only one of" or "at least one of"
+    pub groups: Vec<ArgGroupConfig>,
     pub command_type: CommandType<'a>,
+    // Shown after `--help` and in generated docs
+    pub examples: Vec<String>,
+    // Shown by `<command> --help` instead of the description
+    pub long_description: Option<String>,
+    pub aliases: Vec<String>,
+    // Related commands listed after the examples
+    pub see_also: Vec<String>,
+    // Heading the command is listed under in the app help
+    pub group: Option<String>,
+    // Still runs, but is left out of help, docs and completions
+    pub hidden: bool,
+    // Command that replaces this one, named in a warning on every run
+    pub deprecated: Option<String>,
+    // Run around the action, inside the app hooks
+    pub hooks: Hooks<'a>,
 }
 
 impl<'a> CommandConfig<'a> {
-    pub fn new_standard(
-        name: &'static str,
-        description: &'static str,
-        action: Arc<dyn Fn(&[String]) + Send + Sync + 'a>,
+    pub fn new(
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        command_type: CommandType<'a>,
     ) -> Self {
         CommandConfig {
-            name,
-            description,
+            name: name.into(),
+            description: description.into(),
             args: Vec::new(),
-            command_type: CommandType::Standard(action),
+            groups: Vec::new(),
+            command_type,
+            examples: Vec::new(),
+            long_description: None,
+            aliases: Vec::new(),
+            see_also: Vec::new(),
+            group: None,
+            hidden: false,
+            deprecated: None,
+            hooks: Hooks::new(),
         }
     }
 
+    pub fn new_standard(
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        action: StandardAction<'a>,
+    ) -> Self {
+        CommandConfig::new(name, description, CommandType::Standard(action))
+    }
+
     pub fn new_ui(
-        name: &'static str,
-        description: &'static str,
-        action: Arc<dyn Fn() -> Result<(), io::Error> + Send + Sync + 'a>,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        action: UIAction<'a>,
     ) -> Self {
-        CommandConfig {
-            name,
-            description,
-            args: Vec::new(),
-            command_type: CommandType::UI(action),
-        }
+        CommandConfig::new(name, description, CommandType::UI(action))
     }
 
     pub fn new_ui_with_return(
-        name: &'static str,
-        description: &'static str,
-        action: Arc<dyn Fn() -> Result<Option<String>, io::Error> + Send + Sync + 'a>,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        action: UIWithReturnAction<'a>,
     ) -> Self {
-        CommandConfig {
-            name,
-            description,
-            args: Vec::new(),
-            command_type: CommandType::UIWithReturn(action),
-        }
+        CommandConfig::new(name, description, CommandType::UIWithReturn(action))
+    }
+
+    /// Creates a command whose action returns a future, run on the app's
+    /// `Runtime` by `execute_cli`.
+    pub fn new_async<F, Fut>(
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        action: F,
+    ) -> Self
+    where
+        F: Fn(Context) -> Fut + Send + Sync + 'a,
+        Fut: Future<Output = io::Result<()>> + Send + 'a,
+    {
+        let action: AsyncAction<'a> = Arc::new(move |ctx| Box::pin(action(ctx)));
+        CommandConfig::new(name, description, CommandType::Async(action))
+    }
+
+    /// Creates a command whose action returns a value. With `--json` the
+    /// value is printed as JSON, else strings are printed as they are and
+    /// other values as indented JSON.
+    pub fn new_structured<F, T>(
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        action: F,
+    ) -> Self
+    where
+        F: Fn(&Context) -> io::Result<T> + Send + Sync + 'a,
+        T: Serialize,
+    {
+        let action: StructuredAction<'a> =
+            Arc::new(move |ctx| serde_json::to_value(action(ctx)?).map_err(io::Error::other));
+        CommandConfig::new(name, description, CommandType::Structured(action))
     }
 
     pub fn add_arg(&mut self, arg: ArgConfig) -> &mut Self {
@@ -75,189 +157,677 @@ impl<'a> CommandConfig<'a> {
         self
     }
 
-    // Executes the action associated with this command
-    pub fn execute_action(&self, args: Option<&[String]>) {
+    pub fn add_group(&mut self, group: ArgGroupConfig) -> &mut Self {
+        self.groups.push(group);
+        self
+    }
+
+    pub fn add_example(&mut self, example: impl Into<String>) -> &mut Self {
+        self.examples.push(example.into());
+        self
+    }
+
+    pub fn set_long_description(&mut self, description: impl Into<String>) -> &mut Self {
+        self.long_description = Some(description.into());
+        self
+    }
+
+    pub fn add_alias(&mut self, alias: impl Into<String>) -> &mut Self {
+        self.aliases.push(alias.into());
+        self
+    }
+
+    pub fn add_see_also(&mut self, command: impl Into<String>) -> &mut Self {
+        self.see_also.push(command.into());
+        self
+    }
+
+    pub fn set_group(&mut self, group: impl Into<String>) -> &mut Self {
+        self.group = Some(group.into());
+        self
+    }
+
+    pub fn set_hidden(&mut self, hidden: bool) -> &mut Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Marks the command as deprecated in favor of `replacement`. It keeps
+    /// working, but prints a warning to stderr before running.
+    pub fn deprecate(&mut self, replacement: impl Into<String>) -> &mut Self {
+        self.deprecated = Some(replacement.into());
+        self
+    }
+
+    pub fn deprecation_warning(&self) -> Option<String> {
+        self.deprecated.as_ref().map(|replacement| {
+            format!(
+                "warning: '{}' is deprecated and will be removed, use '{}' instead.",
+                self.name, replacement
+            )
+        })
+    }
+
+    pub fn add_pre_run(&mut self, hook: Hook<'a>) -> &mut Self {
+        self.hooks.pre_run.push(hook);
+        self
+    }
+
+    pub fn add_post_run(&mut self, hook: Hook<'a>) -> &mut Self {
+        self.hooks.post_run.push(hook);
+        self
+    }
+
+    pub fn add_on_error(&mut self, hook: ErrorHook<'a>) -> &mut Self {
+        self.hooks.on_error.push(hook);
+        self
+    }
+
+    pub fn add_finally(&mut self, hook: FinallyHook<'a>) -> &mut Self {
+        self.hooks.finally.push(hook);
+        self
+    }
+
+    // Executes the action associated with this command. Async actions run on
+    // the default runtime, since the command doesn't know the app's.
+    pub fn execute_action(&self, args: Option<&Args>) {
+        let ctx = Context::new(self.name.clone(), args.cloned().unwrap_or_default());
+        self.run_action(&ctx).unwrap();
+    }
+
+    // Runs the action alone, without any hooks or middleware
+    pub fn run_action(&self, ctx: &Context) -> io::Result<()> {
+        self.run_action_on(ctx, None)
+    }
+
+    // Like `run_action`, with async actions on `runtime` if one is given
+    pub(crate) fn run_action_on(
+        &self,
+        ctx: &Context,
+        runtime: Option<&Arc<dyn Runtime>>,
+    ) -> io::Result<()> {
         match &self.command_type {
-            CommandType::Standard(action) => {
-                if let Some(args) = args {
-                    action(args);
+            CommandType::Standard(action) => action(ctx),
+            CommandType::UI(action) => action(ctx)?,
+            CommandType::UIWithReturn(action) => {
+                let result = action(ctx)?;
+                // `--json` prints it once the command is done
+                if let (Some(value), OutputFormat::Text) = (&result, ctx.flags.output) {
+                    writeln!(ctx.stdout(), "{}", value)?;
                 }
+                *ctx.returned.lock().unwrap() = result.map(Value::String);
             }
-            CommandType::UI(action) => action().unwrap(),
-            CommandType::UIWithReturn(action) => {
-                let result = action().unwrap();
-                println!("Result: {:?}", result);
+            CommandType::Async(action) => match runtime {
+                Some(runtime) => runtime.block_on(action(ctx.clone()))?,
+                None => default_runtime()?.block_on(action(ctx.clone()))?,
+            },
+            CommandType::Structured(action) => {
+                let value = action(ctx)?;
+                if ctx.flags.output == OutputFormat::Text {
+                    match &value {
+                        Value::String(text) => writeln!(ctx.stdout(), "{}", text)?,
+                        Value::Null => {}
+                        value => writeln!(ctx.stdout(), "{:#}", value)?,
+                    }
+                }
+                *ctx.returned.lock().unwrap() = Some(value);
             }
         }
+        Ok(())
     }
 }
 
+/// What happens when the app is run without a subcommand.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum BareInvocation {
+    /// Print the app help.
+    #[default]
+    Help,
+    /// Run the named command as if it had been typed.
+    Command(String),
+    /// Pick a command from a menu, then prompt for its required args.
+    Picker,
+}
+
 #[derive(Clone)]
 pub struct CliConfig<'a> {
-    pub app_name: &'static str,
-    pub version: &'static str,
-    pub about: &'static str,
+    pub app_name: Cow<'static, str>,
+    pub version: Cow<'static, str>,
+    pub about: Cow<'static, str>,
     pub commands: Vec<CommandConfig<'a>>,
+    pub env_prefix: Option<Cow<'static, str>>,
+    // Loaded config files, enables the built-in `config` command
+    pub settings: Option<Settings>,
+    // Adds the hidden `generate-docs` command
+    pub docs_command: bool,
+    pub bare_invocation: BareInvocation,
+    // Run around every command
+    pub hooks: Hooks<'a>,
+    pub middleware: Vec<Middleware<'a>>,
+    // Handed to every action through its `Context`
+    pub extensions: Extensions,
+    // Accepted by every command, before or after its name
+    pub global_args: Vec<ArgConfig>,
+    // Runs async actions, a default one is created when unset
+    pub runtime: Option<Arc<dyn Runtime>>,
+    // Where widgets draw, see `ui::backend` for the default
+    pub backend: Option<SharedBackend>,
+    // Colors and glyphs of every widget, `Theme::default()` if unset
+    pub theme: Option<Theme>,
 }
 
 impl<'a> CliConfig<'a> {
-    pub fn new(app_name: &'static str, version: &'static str, about: &'static str) -> Self {
+    pub fn new(
+        app_name: impl Into<Cow<'static, str>>,
+        version: impl Into<Cow<'static, str>>,
+        about: impl Into<Cow<'static, str>>,
+    ) -> Self {
         CliConfig {
-            app_name,
-            version,
-            about,
+            app_name: app_name.into(),
+            version: version.into(),
+            about: about.into(),
             commands: Vec::new(),
+            env_prefix: None,
+            settings: None,
+            docs_command: false,
+            bare_invocation: BareInvocation::Help,
+            hooks: Hooks::new(),
+            middleware: Vec::new(),
+            extensions: Extensions::new(),
+            global_args: Vec::new(),
+            runtime: None,
+            backend: None,
+            theme: None,
         }
     }
 
+    pub fn set_runtime(&mut self, runtime: Arc<dyn Runtime>) -> &mut Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
+    pub fn set_backend(&mut self, backend: impl Backend + 'static) -> &mut Self {
+        self.backend = Some(Arc::new(Mutex::new(backend)));
+        self
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) -> &mut Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    /// Uses a theme file, see `Theme::from_file`.
+    pub fn load_theme(&mut self, path: impl AsRef<Path>) -> io::Result<&mut Self> {
+        let theme = Theme::from_file(path.as_ref())?;
+        Ok(self.set_theme(theme))
+    }
+
+    pub fn add_global_arg(&mut self, arg: ArgConfig) -> &mut Self {
+        self.global_args.push(arg);
+        self
+    }
+
+    /// Makes `value` available to every action as `ctx.extension::<T>()`.
+    pub fn insert_extension<T: Any + Send + Sync>(&mut self, value: T) -> &mut Self {
+        self.extensions.insert(value);
+        self
+    }
+
+    pub fn add_pre_run(&mut self, hook: Hook<'a>) -> &mut Self {
+        self.hooks.pre_run.push(hook);
+        self
+    }
+
+    pub fn add_post_run(&mut self, hook: Hook<'a>) -> &mut Self {
+        self.hooks.post_run.push(hook);
+        self
+    }
+
+    pub fn add_on_error(&mut self, hook: ErrorHook<'a>) -> &mut Self {
+        self.hooks.on_error.push(hook);
+        self
+    }
+
+    pub fn add_finally(&mut self, hook: FinallyHook<'a>) -> &mut Self {
+        self.hooks.finally.push(hook);
+        self
+    }
+
+    /// Adds a middleware around every action. The first one added is the
+    /// outermost.
+    pub fn add_middleware(&mut self, middleware: Middleware<'a>) -> &mut Self {
+        self.middleware.push(middleware);
+        self
+    }
+
+    pub fn set_bare_invocation(&mut self, behavior: BareInvocation) -> &mut Self {
+        self.bare_invocation = behavior;
+        self
+    }
+
+    pub fn enable_docs_command(&mut self) -> &mut Self {
+        self.docs_command = true;
+        self
+    }
+
+    // Loads the system, user and project config files of the app. Their
+    // values become arg defaults, below the command line and env vars
+    pub fn load_config_files(&mut self) -> io::Result<&mut Self> {
+        self.settings = Some(Settings::load(&self.app_name)?);
+        Ok(self)
+    }
+
+    pub fn set_settings(&mut self, settings: Settings) -> &mut Self {
+        self.settings = Some(settings);
+        self
+    }
+
+    // Prepended to the `env` name of every arg, e.g. `MYAPP_` turns `TOKEN`
+    // into `MYAPP_TOKEN`
+    pub fn set_env_prefix(&mut self, prefix: impl Into<Cow<'static, str>>) -> &mut Self {
+        self.env_prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn env_var_name(&self, arg: &ArgConfig) -> Option<String> {
+        arg.env
+            .as_ref()
+            .map(|env| format!("{}{}", self.env_prefix.as_deref().unwrap_or_default(), env))
+    }
+
     pub fn add_command(&mut self, command: CommandConfig<'a>) -> &mut Self {
         self.commands.push(command);
         self
     }
 
-    // Helper functions to add rustubble TUI's commands
+    pub(crate) fn push_command(&mut self, command: CommandConfig<'a>) -> &mut CommandConfig<'a> {
+        self.commands.push(command);
+        self.commands.last_mut().unwrap()
+    }
+
+    // Helper functions to add rustubble TUI's commands. Each one returns the
+    // created command so args can be added to it, and has a `_with` variant
+    // taking a `Provider` that is called when the command runs.
+    //
+    // Prompting helpers also register a flag (`--value`, `--text` or
+    // `--select`) that skips the prompt when given on the command line.
 
     pub fn add_input(
         &mut self,
-        name: &'static str,
-        description: &'static str,
-        placeholder: &'static str,
-        initial_text: &'static str,
-        label: &'static str,
-    ) {
-        let input_action = move || input_form(placeholder, initial_text, label);
-
-        let command = CommandConfig::new_ui_with_return(name, description, Arc::new(input_action));
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        placeholder: impl Into<String>,
+        initial_text: impl Into<String>,
+        label: impl Into<String>,
+    ) -> &mut CommandConfig<'a> {
+        let initial_text = initial_text.into();
+        self.add_input_with(
+            name,
+            description,
+            placeholder,
+            label,
+            Arc::new(move |_| initial_text.clone()),
+        )
+    }
 
-        self.add_command(command);
+    pub fn add_input_with(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        placeholder: impl Into<String>,
+        label: impl Into<String>,
+        initial_text: Provider<'a, String>,
+    ) -> &mut CommandConfig<'a> {
+        let (placeholder, label) = (placeholder.into(), label.into());
+        let input_action = move |ctx: &Context| match ctx.get("value") {
+            Some(value) => Ok(Some(value.to_string())),
+            None => input_form(&placeholder, &initial_text(ctx), &label),
+        };
+
+        let mut command =
+            CommandConfig::new_ui_with_return(name, description, Arc::new(input_action));
+        command.add_arg(ArgConfig::new("value", "Value to use instead of prompting").long("value"));
+
+        self.push_command(command)
     }
 
     pub fn add_text_area(
         &mut self,
-        name: &'static str,
-        description: &'static str,
-        label: &'a str,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        label: impl Into<String>,
         visible_lines: usize,
-    ) {
-        let text_area = move || text_area(label, visible_lines);
+    ) -> &mut CommandConfig<'a> {
+        let label = label.into();
+        self.add_text_area_with(
+            name,
+            description,
+            visible_lines,
+            Arc::new(move |_| label.clone()),
+        )
+    }
 
-        let command = CommandConfig::new_ui(name, description, Arc::new(text_area));
+    pub fn add_text_area_with(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        visible_lines: usize,
+        label: Provider<'a, String>,
+    ) -> &mut CommandConfig<'a> {
+        self.add_text_area_with_options(
+            name,
+            description,
+            visible_lines,
+            label,
+            TextAreaOptions::default(),
+        )
+    }
+
+    /// A text area with initial text and limits. Besides `--text`, the
+    /// command takes `--editor` to write the text in `$EDITOR` instead,
+    /// unless `options.editor` is off.
+    pub fn add_text_area_with_options(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        visible_lines: usize,
+        label: Provider<'a, String>,
+        options: TextAreaOptions,
+    ) -> &mut CommandConfig<'a> {
+        let editor = options.editor;
+        let text_area = move |ctx: &Context| {
+            let text = match ctx.get("text") {
+                Some(text) => text.to_string(),
+                None if ctx.contains("editor") => open_editor(&options.initial_text)?,
+                None => return text_area_with(&label(ctx), visible_lines, &options),
+            };
+            options.check(&text)?;
+            Ok(Some(text))
+        };
+
+        let mut command = CommandConfig::new_ui_with_return(name, description, Arc::new(text_area));
+        command.add_arg(ArgConfig::new("text", "Text to use instead of prompting").long("text"));
+        if editor {
+            command.add_arg(
+                ArgConfig::new("editor", "Write the text in $EDITOR")
+                    .long("editor")
+                    .flag()
+                    .conflicts_with("text"),
+            );
+        }
 
-        self.add_command(command);
+        self.push_command(command)
     }
 
     pub fn add_loader(
         &mut self,
-        name: &'static str,
-        description: &'static str,
-        text: &'a str,
-        style: &'a str,
-    ) {
-        let loading = move || loader(text.to_string(), style.to_string());
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        text: impl Into<String>,
+        style: impl Into<String>,
+    ) -> &mut CommandConfig<'a> {
+        let text = text.into();
+        self.add_loader_with(name, description, style, Arc::new(move |_| text.clone()))
+    }
+
+    pub fn add_loader_with(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        style: impl Into<String>,
+        text: Provider<'a, String>,
+    ) -> &mut CommandConfig<'a> {
+        let style = style.into();
+        let loading = move |ctx: &Context| loader(text(ctx), style.clone());
 
         let command = CommandConfig::new_ui(name, description, Arc::new(loading));
-        self.add_command(command);
+        self.push_command(command)
     }
 
     pub fn add_table(
         &mut self,
-        name: &'static str,
-        description: &'static str,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
         headers: Vec<&'static str>,
         rows: Vec<Vec<&'static str>>,
-    ) {
-        let table = move || table(headers.clone(), rows.clone());
+    ) -> &mut CommandConfig<'a> {
+        let data = TableData {
+            headers: headers.iter().map(|s| s.to_string()).collect(),
+            rows: rows
+                .iter()
+                .map(|row| row.iter().map(|s| s.to_string()).collect())
+                .collect(),
+        };
+
+        self.add_table_with(name, description, Arc::new(move |_| data.clone()))
+    }
+
+    pub fn add_table_with(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        data: Provider<'a, TableData>,
+    ) -> &mut CommandConfig<'a> {
+        let table = move |ctx: &Context| {
+            let TableData { headers, rows } = data(ctx);
+            table(headers, rows)
+        };
 
         let command = CommandConfig::new_ui(name, description, Arc::new(table));
-        self.add_command(command);
+        self.push_command(command)
     }
 
+    /// A progress bar filling up in the primary and accent colors of the
+    /// theme.
     pub fn add_progress_bar(
         &mut self,
-        name: &'static str,
-        description: &'static str,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
         progress: f32,
         length: u16,
-        prefix: &'static str,
-        start_color: Color,
-        end_color: Color,
-    ) {
-        let progress_timed =
-            move || timed_progress(prefix, progress, length, start_color, end_color);
+        prefix: impl Into<String>,
+    ) -> &mut CommandConfig<'a> {
+        self.add_progress_bar_with(
+            name,
+            description,
+            length,
+            prefix,
+            Arc::new(move |_| progress),
+        )
+    }
+
+    pub fn add_progress_bar_with(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        length: u16,
+        prefix: impl Into<String>,
+        progress: Provider<'a, f32>,
+    ) -> &mut CommandConfig<'a> {
+        let prefix = prefix.into();
+        let progress_timed = move |ctx: &Context| {
+            let theme = ui::theme();
+            timed_progress(&prefix, progress(ctx), length, theme.primary, theme.accent)
+        };
         let command = CommandConfig::new_ui(name, description, Arc::new(progress_timed));
-        self.add_command(command);
+        self.push_command(command)
     }
 
     pub fn add_timer(
         &mut self,
-        name: &'static str,
-        description: &'static str,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
         secs: u64,
         nanos: u32,
-    ) {
-        let timeed = move || timer(secs, nanos);
+    ) -> &mut CommandConfig<'a> {
+        self.add_timer_with(
+            name,
+            description,
+            Arc::new(move |_| Duration::new(secs, nanos)),
+        )
+    }
+
+    pub fn add_timer_with(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        duration: Provider<'a, Duration>,
+    ) -> &mut CommandConfig<'a> {
+        let timeed = move |ctx: &Context| {
+            let duration = duration(ctx);
+            timer(duration.as_secs(), duration.subsec_nanos())
+        };
 
         let command = CommandConfig::new_ui(name, description, Arc::new(timeed));
 
-        self.add_command(command);
+        self.push_command(command)
     }
 
-    pub fn add_stopwatch(&mut self, name: &'static str, description: &'static str) {
-        let timeed = move || stopwatch();
+    pub fn add_stopwatch(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+    ) -> &mut CommandConfig<'a> {
+        let timeed = move |_: &Context| stopwatch();
         let command = CommandConfig::new_ui(name, description, Arc::new(timeed));
-        self.add_command(command);
+        self.push_command(command)
     }
 
     pub fn add_viewport(
         &mut self,
-        name: &'static str,
-        description: &'static str,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
         file_path: String,
-    ) {
-        let viewport = move || viewport(file_path.clone());
+    ) -> &mut CommandConfig<'a> {
+        self.add_viewport_with(name, description, Arc::new(move |_| file_path.clone()))
+    }
+
+    pub fn add_viewport_with(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        file_path: Provider<'a, String>,
+    ) -> &mut CommandConfig<'a> {
+        let viewport = move |ctx: &Context| viewport(file_path(ctx));
 
         let command = CommandConfig::new_ui(name, description, Arc::new(viewport));
 
-        self.add_command(command);
+        self.push_command(command)
     }
 
     pub fn add_item_list(
         &mut self,
-        name: &'static str,
-        description: &'static str,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
         list: Vec<Item>,
         list_title: String,
-    ) {
-        let new_item_list = move || item_list(list.clone(), list_title.clone());
+    ) -> &mut CommandConfig<'a> {
+        self.add_item_list_with(
+            name,
+            description,
+            list_title,
+            Arc::new(move |_| list.clone()),
+        )
+    }
+
+    pub fn add_item_list_with(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        list_title: String,
+        list: Provider<'a, Vec<Item>>,
+    ) -> &mut CommandConfig<'a> {
+        let new_item_list = move |ctx: &Context| {
+            let list = list(ctx);
+            match ctx.get("select") {
+                Some(title) => {
+                    select_option(title, list.iter().map(|item| item.title.as_str())).map(Some)
+                }
+                None => item_list(list, list_title.clone()),
+            }
+        };
 
-        let command = CommandConfig::new_ui_with_return(name, description, Arc::new(new_item_list));
+        let mut command =
+            CommandConfig::new_ui_with_return(name, description, Arc::new(new_item_list));
+        command.add_arg(
+            ArgConfig::new("select", "Item to select instead of prompting").long("select"),
+        );
 
-        self.add_command(command);
+        self.push_command(command)
     }
 
     pub fn add_menu_list(
         &mut self,
-        name: &'static str,
-        description: &'static str,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
         list_title: String,
         list_subtitle: String,
         list: Vec<String>,
-    ) {
-        let new_menu_list =
-            move || menu_list(list.clone(), list_title.clone(), list_subtitle.clone());
-        let command = CommandConfig::new_ui_with_return(name, description, Arc::new(new_menu_list));
-        self.add_command(command);
+    ) -> &mut CommandConfig<'a> {
+        let options = list.clone();
+        let command = self.add_menu_list_with(
+            name,
+            description,
+            list_title,
+            list_subtitle,
+            Arc::new(move |_| list.clone()),
+        );
+        // The options are known up front, so `--select` can complete them
+        if let Some(select) = command.args.iter_mut().find(|arg| arg.name == "select") {
+            select.completer = Some(Arc::new(move |_| options.clone()));
+        }
+        command
     }
 
-    pub fn commands(&self) -> &[CommandConfig] {
+    pub fn add_menu_list_with(
+        &mut self,
+        name: impl Into<Cow<'static, str>>,
+        description: impl Into<Cow<'static, str>>,
+        list_title: String,
+        list_subtitle: String,
+        list: Provider<'a, Vec<String>>,
+    ) -> &mut CommandConfig<'a> {
+        let new_menu_list = move |ctx: &Context| {
+            let list = list(ctx);
+            match ctx.get("select") {
+                Some(option) => select_option(option, list.iter().map(String::as_str)).map(Some),
+                None => menu_list(list, list_title.clone(), list_subtitle.clone()),
+            }
+        };
+        let mut command =
+            CommandConfig::new_ui_with_return(name, description, Arc::new(new_menu_list));
+        command.add_arg(
+            ArgConfig::new("select", "Option to select instead of prompting").long("select"),
+        );
+        self.push_command(command)
+    }
+
+    pub fn commands(&self) -> &[CommandConfig<'a>] {
         &self.commands
     }
 }
 
+// The option `--select` names. Scripts get an error rather than a prompt
+// when it names none.
+fn select_option<'o>(selected: &str, options: impl Iterator<Item = &'o str>) -> io::Result<String> {
+    let options: Vec<&str> = options.collect();
+    if options.contains(&selected) {
+        return Ok(selected.to_string());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+            "'{}' is not an option, expected one of: {}",
+            selected,
+            options.join(", ")
+        ),
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::sync::Arc;
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn test_cli_config() {
@@ -272,9 +842,10 @@ mod tests {
         );
 
         command.add_arg(ArgConfig {
-            name: "message",
+            name: "message".into(),
             help: "A test message".to_string(),
             required: true,
+            ..Default::default()
         });
 
         cli_config.add_command(command);
@@ -284,4 +855,115 @@ mod tests {
         assert_eq!(cli_config.about, "A test application");
         assert_eq!(cli_config.commands().len(), 1);
     }
+
+    #[test]
+    fn test_helpers_return_command_for_args() {
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+
+        cli_config
+            .add_menu_list_with(
+                "pick",
+                "Pick a file",
+                "Files".to_string(),
+                "Select one".to_string(),
+                Arc::new(|ctx: &Context| ctx.to_vec()),
+            )
+            .add_arg(ArgConfig {
+                name: "dir".into(),
+                help: "Directory to list".to_string(),
+                required: false,
+                ..Default::default()
+            });
+
+        let command = &cli_config.commands()[0];
+        assert_eq!(command.name, "pick");
+        assert_eq!(command.args.len(), 2);
+        assert_eq!(command.args[1].name, "dir");
+        assert!(matches!(command.command_type, CommandType::UIWithReturn(_)));
+    }
+
+    #[test]
+    fn test_provider_receives_args_when_command_runs() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        let provider_seen = seen.clone();
+        cli_config.add_menu_list_with(
+            "pick",
+            "Pick a file",
+            "Files".to_string(),
+            "Select one".to_string(),
+            Arc::new(move |ctx: &Context| {
+                let dir = ctx.get("dir").unwrap_or(".").to_string();
+                provider_seen.lock().unwrap().push(dir.clone());
+                vec![format!("{}/a.txt", dir), format!("{}/b.txt", dir)]
+            }),
+        );
+        assert!(seen.lock().unwrap().is_empty());
+
+        let mut args = Args::new();
+        args.insert("dir", "src");
+        args.insert("select", "src/b.txt");
+        match &cli_config.commands()[0].command_type {
+            CommandType::UIWithReturn(action) => assert_eq!(
+                action(&Context::new("pick", args)).unwrap(),
+                Some("src/b.txt".to_string())
+            ),
+            _ => panic!("menu list should return a value"),
+        }
+        assert_eq!(*seen.lock().unwrap(), vec!["src"]);
+    }
+
+    #[test]
+    fn test_prompt_skipped_when_value_given() {
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        cli_config.add_input("name", "Ask for a name", "Your name", "", "Name");
+        cli_config.add_menu_list(
+            "color",
+            "Pick a color",
+            "Colors".to_string(),
+            "Select one".to_string(),
+            vec!["red".to_string(), "blue".to_string()],
+        );
+
+        let input = &cli_config.commands()[0];
+        assert_eq!(input.args[0].long.as_deref(), Some("value"));
+
+        let mut args = Args::new();
+        args.insert("value", "Ferris");
+        match &input.command_type {
+            CommandType::UIWithReturn(action) => {
+                assert_eq!(
+                    action(&Context::new("name", args)).unwrap(),
+                    Some("Ferris".to_string())
+                )
+            }
+            _ => panic!("input should return a value"),
+        }
+
+        let mut args = Args::new();
+        args.insert("select", "blue");
+        match &cli_config.commands()[1].command_type {
+            CommandType::UIWithReturn(action) => {
+                assert_eq!(
+                    action(&Context::new("color", args)).unwrap(),
+                    Some("blue".to_string())
+                )
+            }
+            _ => panic!("menu list should return a value"),
+        }
+
+        let mut args = Args::new();
+        args.insert("select", "green");
+        match &cli_config.commands()[1].command_type {
+            CommandType::UIWithReturn(action) => {
+                let err = action(&Context::new("color", args)).unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+                assert_eq!(
+                    err.to_string(),
+                    "'green' is not an option, expected one of: red, blue"
+                );
+            }
+            _ => panic!("menu list should return a value"),
+        }
+    }
 }
@@ -0,0 +1,474 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::future::Future;
+use std::io::{self, BufRead, BufReader, Cursor, IsTerminal, Read, Write};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+use clap::{Arg, ArgAction, ArgMatches};
+use serde_json::Value;
+
+use crate::config::Args;
+use crate::output::{emit, Level};
+use crate::settings::Settings;
+use crate::signals::CancellationToken;
+use crate::ui;
+use crate::wrappers::{
+    input_form, loader, menu_list, progress_while, spin_while, timed_progress, ProgressHandle,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+/// Flags that apply to every command.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GlobalFlags {
+    // Number of times `-v` was given
+    pub verbose: u8,
+    pub quiet: bool,
+    pub color: ColorChoice,
+    pub output: OutputFormat,
+    // Prompts fail instead of waiting for a user
+    pub no_input: bool,
+}
+
+impl GlobalFlags {
+    /// Reads the standard global args, leaving out any the app replaced.
+    pub fn from_matches(matches: &ArgMatches) -> Self {
+        let flag = |id: &str| matches.try_get_one::<bool>(id).ok().flatten() == Some(&true);
+        let color = match matches.try_get_one::<String>("color").ok().flatten() {
+            Some(color) if color == "always" => ColorChoice::Always,
+            Some(color) if color == "never" => ColorChoice::Never,
+            _ => ColorChoice::Auto,
+        };
+        GlobalFlags {
+            verbose: matches
+                .try_get_one::<u8>("verbose")
+                .ok()
+                .flatten()
+                .copied()
+                .unwrap_or_default(),
+            quiet: flag("quiet"),
+            color,
+            output: if flag("json") {
+                OutputFormat::Json
+            } else {
+                OutputFormat::Text
+            },
+            no_input: flag("no-input"),
+        }
+    }
+}
+
+// Added to every app by `build_cli`, unless a user arg takes their name
+pub(crate) fn standard_global_args() -> Vec<Arg> {
+    vec![
+        Arg::new("quiet")
+            .short('q')
+            .long("quiet")
+            .action(ArgAction::SetTrue)
+            .help("Only print errors"),
+        Arg::new("verbose")
+            .short('v')
+            .long("verbose")
+            .action(ArgAction::Count)
+            .help("Print more details, repeat for even more"),
+        Arg::new("color")
+            .long("color")
+            .value_name("WHEN")
+            .value_parser(["auto", "always", "never"])
+            .default_value("auto")
+            .help("When to use colors"),
+        Arg::new("json")
+            .long("json")
+            .action(ArgAction::SetTrue)
+            .help("Print results and errors as JSON"),
+        Arg::new("no-input")
+            .long("no-input")
+            .action(ArgAction::SetTrue)
+            .help("Fail instead of prompting for input"),
+    ]
+    .into_iter()
+    .map(|arg| arg.global(true).help_heading("Global Options"))
+    .collect()
+}
+
+/// What the terminal the app runs in can do.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Terminal {
+    pub stdin_tty: bool,
+    pub stdout_tty: bool,
+    pub stderr_tty: bool,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Default for Terminal {
+    // A pipe with the conventional 80x24 size
+    fn default() -> Self {
+        Terminal {
+            stdin_tty: false,
+            stdout_tty: false,
+            stderr_tty: false,
+            width: 80,
+            height: 24,
+        }
+    }
+}
+
+impl Terminal {
+    pub fn detect() -> Self {
+        let (width, height) = crossterm::terminal::size().unwrap_or((80, 24));
+        Terminal {
+            stdin_tty: io::stdin().is_terminal(),
+            stdout_tty: io::stdout().is_terminal(),
+            stderr_tty: io::stderr().is_terminal(),
+            width,
+            height,
+        }
+    }
+
+    // Prompts need a user at both ends
+    pub fn is_interactive(&self) -> bool {
+        self.stdin_tty && self.stdout_tty
+    }
+}
+
+type SharedWriter = Arc<Mutex<Box<dyn Write + Send>>>;
+
+// Output copied by `Io::capture`
+pub(crate) type Captured = Arc<Mutex<Vec<u8>>>;
+
+/// A writer shared by every clone of an `Io`.
+#[derive(Clone)]
+pub struct Stream(SharedWriter);
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
+
+// Writes to a stream and keeps a copy of what was written
+struct Tee(Stream, Captured);
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.0.write(buf)?;
+        self.1.lock().unwrap().extend_from_slice(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// The standard streams of a run.
+///
+/// Actions should write through `ctx.stdout()` and `ctx.stderr()` rather than
+/// `println!`, so that `run_from` can capture what they print.
+#[derive(Clone)]
+pub struct Io {
+    stdin: Arc<Mutex<Box<dyn BufRead + Send>>>,
+    stdout: SharedWriter,
+    stderr: SharedWriter,
+    // Whether these are the streams of the process
+    std: bool,
+}
+
+impl fmt::Debug for Io {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Io").field("std", &self.std).finish()
+    }
+}
+
+impl Default for Io {
+    fn default() -> Self {
+        Io::std()
+    }
+}
+
+impl Io {
+    /// The streams of the process.
+    pub fn std() -> Self {
+        Io {
+            std: true,
+            ..Io::new(io::stdin(), io::stdout(), io::stderr())
+        }
+    }
+
+    pub fn new(
+        stdin: impl Read + Send + 'static,
+        stdout: impl Write + Send + 'static,
+        stderr: impl Write + Send + 'static,
+    ) -> Self {
+        Io {
+            stdin: Arc::new(Mutex::new(Box::new(BufReader::new(stdin)))),
+            stdout: Arc::new(Mutex::new(Box::new(stdout))),
+            stderr: Arc::new(Mutex::new(Box::new(stderr))),
+            std: false,
+        }
+    }
+
+    /// Reads `input` and throws the output away, which `run_from` still
+    /// captures.
+    pub fn with_stdin(input: impl Into<Vec<u8>>) -> Self {
+        Io::new(Cursor::new(input.into()), io::sink(), io::sink())
+    }
+
+    /// No input and no output besides what `run_from` captures.
+    pub fn null() -> Self {
+        Io::with_stdin(Vec::new())
+    }
+
+    pub fn stdout(&self) -> Stream {
+        Stream(self.stdout.clone())
+    }
+
+    pub fn stderr(&self) -> Stream {
+        Stream(self.stderr.clone())
+    }
+
+    /// Reads a line without its line ending, or `None` at the end of input.
+    pub fn read_line(&self) -> io::Result<Option<String>> {
+        let mut line = String::new();
+        if self.stdin.lock().unwrap().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let len = line.trim_end_matches(['\r', '\n']).len();
+        line.truncate(len);
+        Ok(Some(line))
+    }
+
+    pub fn read_to_string(&self) -> io::Result<String> {
+        let mut input = String::new();
+        self.stdin.lock().unwrap().read_to_string(&mut input)?;
+        Ok(input)
+    }
+
+    pub(crate) fn is_std(&self) -> bool {
+        self.std
+    }
+
+    // The terminal behind the streams, a pipe unless they are the process's
+    pub(crate) fn terminal(&self) -> Terminal {
+        if self.std {
+            Terminal::detect()
+        } else {
+            Terminal::default()
+        }
+    }
+
+    // The same streams, also copying the output into the returned buffers
+    pub(crate) fn capture(&self) -> (Io, Captured, Captured) {
+        let (stdout, stderr) = (Captured::default(), Captured::default());
+        let io = Io {
+            stdin: self.stdin.clone(),
+            stdout: Arc::new(Mutex::new(Box::new(Tee(self.stdout(), stdout.clone())))),
+            stderr: Arc::new(Mutex::new(Box::new(Tee(self.stderr(), stderr.clone())))),
+            std: self.std,
+        };
+        (io, stdout, stderr)
+    }
+}
+
+/// User state shared with every action, keyed by type.
+#[derive(Clone, Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Extensions::default()
+    }
+
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) -> &mut Self {
+        self.map.insert(TypeId::of::<T>(), Arc::new(value));
+        self
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+}
+
+/// Everything an action knows about the current run.
+///
+/// Derefs to the parsed `Args`, so `ctx.get("name")` looks up an arg value.
+#[derive(Clone, Debug, Default)]
+pub struct Context {
+    pub command: String,
+    pub args: Args,
+    pub flags: GlobalFlags,
+    pub terminal: Terminal,
+    // Loaded config files, if the app enabled them
+    pub settings: Option<Settings>,
+    pub extensions: Extensions,
+    // Cancelled by Ctrl+C or SIGTERM while the command runs
+    pub cancel: CancellationToken,
+    pub io: Io,
+    // Value returned by the action, reported by `run_from` and `--json`
+    pub(crate) returned: Arc<Mutex<Option<Value>>>,
+}
+
+impl Context {
+    pub fn new(command: impl Into<String>, args: Args) -> Self {
+        Context {
+            command: command.into(),
+            args,
+            ..Default::default()
+        }
+    }
+
+    pub fn extension<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.extensions.get()
+    }
+
+    pub fn insert_extension<T: Any + Send + Sync>(&mut self, value: T) -> &mut Self {
+        self.extensions.insert(value);
+        self
+    }
+
+    pub fn stdout(&self) -> Stream {
+        self.io.stdout()
+    }
+
+    pub fn stderr(&self) -> Stream {
+        self.io.stderr()
+    }
+
+    // Messages for the user on stderr, see `output::emit`
+
+    pub fn info(&self, message: impl Display) {
+        emit(&self.io, &self.flags, Level::Info, message);
+    }
+
+    pub fn success(&self, message: impl Display) {
+        emit(&self.io, &self.flags, Level::Success, message);
+    }
+
+    pub fn warn(&self, message: impl Display) {
+        emit(&self.io, &self.flags, Level::Warn, message);
+    }
+
+    pub fn error(&self, message: impl Display) {
+        emit(&self.io, &self.flags, Level::Error, message);
+    }
+
+    /// Only shown with `-v`.
+    pub fn debug(&self, message: impl Display) {
+        emit(&self.io, &self.flags, Level::Debug, message);
+    }
+
+    // Helpers to show widgets from inside an action, styled by the theme
+
+    pub fn prompt(&self, label: &str, placeholder: &str) -> io::Result<Option<String>> {
+        input_form(placeholder, "", label)
+    }
+
+    pub fn select(&self, title: &str, options: Vec<String>) -> io::Result<Option<String>> {
+        menu_list(options, title.to_string(), String::new())
+    }
+
+    pub fn spinner(&self, text: &str) -> io::Result<()> {
+        loader(text.to_string(), String::new())
+    }
+
+    /// Shows a spinner while `future` runs, for async actions.
+    pub async fn spin_while<F: Future>(&self, message: &str, future: F) -> F::Output {
+        spin_while(message, future).await
+    }
+
+    /// Shows a progress bar while the task runs, see `wrappers::progress_while`.
+    pub async fn progress_while<T, Fut>(&self, prefix: &str, task: T) -> Fut::Output
+    where
+        T: FnOnce(ProgressHandle) -> Fut,
+        Fut: Future,
+    {
+        progress_while(prefix, task).await
+    }
+
+    pub fn progress(&self, prefix: &str, progress: f32) -> io::Result<()> {
+        let theme = ui::theme();
+        timed_progress(prefix, progress, 40, theme.primary, theme.accent)
+    }
+}
+
+impl Deref for Context {
+    type Target = Args;
+
+    fn deref(&self) -> &Self::Target {
+        &self.args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_args_and_extensions() {
+        struct Client {
+            url: String,
+        }
+
+        let mut args = Args::new();
+        args.insert("name", "Ferris");
+        let mut ctx = Context::new("greet", args);
+        ctx.insert_extension(Client {
+            url: "https://example.com".to_string(),
+        });
+
+        assert_eq!(ctx.get("name"), Some("Ferris"));
+        assert_eq!(
+            ctx.extension::<Client>().map(|client| client.url.as_str()),
+            Some("https://example.com")
+        );
+        assert!(ctx.extension::<String>().is_none());
+        assert!(!ctx.terminal.is_interactive());
+    }
+
+    #[test]
+    fn test_io_reads_lines_and_captures_output() {
+        let (io, stdout, _) = Io::with_stdin("one\r\ntwo").capture();
+        assert_eq!(io.read_line().unwrap().as_deref(), Some("one"));
+        assert_eq!(io.read_line().unwrap().as_deref(), Some("two"));
+        assert_eq!(io.read_line().unwrap(), None);
+
+        let ctx = Context {
+            io,
+            ..Default::default()
+        };
+        writeln!(ctx.stdout(), "hello").unwrap();
+        assert_eq!(*stdout.lock().unwrap(), b"hello\n");
+    }
+}
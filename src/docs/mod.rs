@@ -0,0 +1,348 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use clap::{value_parser, Arg, ArgMatches, Command, ValueEnum};
+use clap_mangen::Man;
+
+use crate::cli_builder::build_cli;
+use crate::config::CliConfig;
+use crate::context::Io;
+
+// Also parses `generate-docs --format`, so the names it accepts live here
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum DocFormat {
+    Man,
+    #[value(alias = "md")]
+    Markdown,
+    Html,
+}
+
+// What the Markdown and HTML renderers need to know about one command
+struct CommandDoc {
+    path: String,
+    about: String,
+    usage: String,
+    args: Vec<ArgDoc>,
+    subcommands: Vec<(String, String)>,
+    examples: Option<String>,
+}
+
+struct ArgDoc {
+    name: String,
+    help: String,
+    env: Option<String>,
+    default: Option<String>,
+    required: bool,
+}
+
+/// Renders a man page for the app and one for every visible subcommand,
+/// returned as `(file name, roff)` pairs.
+pub fn render_man_pages(cli_config: &CliConfig) -> io::Result<Vec<(String, Vec<u8>)>> {
+    let mut app = build_cli(cli_config).disable_help_subcommand(true);
+    app.build();
+
+    let mut pages = Vec::new();
+    for cmd in visible_commands(&app) {
+        let man = Man::new(cmd.clone());
+        let mut page = Vec::new();
+        man.render(&mut page)?;
+        pages.push((man.get_filename(), page));
+    }
+    Ok(pages)
+}
+
+/// Renders the whole command tree as a single Markdown reference.
+pub fn render_markdown(cli_config: &CliConfig) -> String {
+    let mut out = String::new();
+    for (i, doc) in command_docs(cli_config).iter().enumerate() {
+        let heading = if i == 0 { "#" } else { "##" };
+        out.push_str(&format!("{} `{}`\n\n", heading, doc.path));
+        if !doc.about.is_empty() {
+            out.push_str(&format!("{}\n\n", doc.about));
+        }
+        out.push_str(&format!("```\n{}\n```\n\n", doc.usage));
+
+        if !doc.args.is_empty() {
+            out.push_str("| Argument | Description | Environment | Default |\n");
+            out.push_str("| --- | --- | --- | --- |\n");
+            for arg in doc.args.iter() {
+                let required = if arg.required { " (required)" } else { "" };
+                out.push_str(&format!(
+                    "| `{}` | {}{} | {} | {} |\n",
+                    arg.name,
+                    arg.help.replace('|', "\\|"),
+                    required,
+                    arg.env.as_deref().map(code).unwrap_or_default(),
+                    arg.default.as_deref().map(code).unwrap_or_default(),
+                ));
+            }
+            out.push('\n');
+        }
+
+        if !doc.subcommands.is_empty() {
+            out.push_str("Commands:\n\n");
+            for (path, about) in doc.subcommands.iter() {
+                out.push_str(&format!("- [`{}`](#{}): {}\n", path, anchor(path), about));
+            }
+            out.push('\n');
+        }
+
+        if let Some(examples) = &doc.examples {
+            out.push_str(&format!("```\n{}\n```\n\n", examples.trim_end()));
+        }
+    }
+    out
+}
+
+/// Renders the whole command tree as a standalone HTML page.
+pub fn render_html(cli_config: &CliConfig) -> String {
+    let docs = command_docs(cli_config);
+    let title = docs.first().map(|doc| doc.path.clone()).unwrap_or_default();
+
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n",
+        escape(&title)
+    );
+    for (i, doc) in docs.iter().enumerate() {
+        let tag = if i == 0 { "h1" } else { "h2" };
+        out.push_str(&format!(
+            "<{tag} id=\"{}\"><code>{}</code></{tag}>\n",
+            anchor(&doc.path),
+            escape(&doc.path)
+        ));
+        if !doc.about.is_empty() {
+            out.push_str(&format!("<p>{}</p>\n", escape(&doc.about)));
+        }
+        out.push_str(&format!("<pre>{}</pre>\n", escape(&doc.usage)));
+
+        if !doc.args.is_empty() {
+            out.push_str("<table>\n<tr><th>Argument</th><th>Description</th><th>Environment</th><th>Default</th></tr>\n");
+            for arg in doc.args.iter() {
+                let required = if arg.required { " (required)" } else { "" };
+                out.push_str(&format!(
+                    "<tr><td><code>{}</code></td><td>{}{}</td><td>{}</td><td>{}</td></tr>\n",
+                    escape(&arg.name),
+                    escape(&arg.help),
+                    required,
+                    escape(arg.env.as_deref().unwrap_or_default()),
+                    escape(arg.default.as_deref().unwrap_or_default()),
+                ));
+            }
+            out.push_str("</table>\n");
+        }
+
+        if !doc.subcommands.is_empty() {
+            out.push_str("<ul>\n");
+            for (path, about) in doc.subcommands.iter() {
+                out.push_str(&format!(
+                    "<li><a href=\"#{}\"><code>{}</code></a>: {}</li>\n",
+                    anchor(path),
+                    escape(path),
+                    escape(about)
+                ));
+            }
+            out.push_str("</ul>\n");
+        }
+
+        if let Some(examples) = &doc.examples {
+            out.push_str(&format!("<pre>{}</pre>\n", escape(examples.trim_end())));
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Writes the docs of a CLI into `out_dir`, returning the created files.
+pub fn generate_docs(
+    cli_config: &CliConfig,
+    format: DocFormat,
+    out_dir: &Path,
+) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(out_dir)?;
+    let name = crate::settings::file_name(&cli_config.app_name);
+
+    let files = match format {
+        DocFormat::Man => render_man_pages(cli_config)?,
+        DocFormat::Markdown => vec![(format!("{}.md", name), render_markdown(cli_config).into())],
+        DocFormat::Html => vec![(format!("{}.html", name), render_html(cli_config).into())],
+    };
+
+    let mut paths = Vec::new();
+    for (file_name, content) in files {
+        let path = out_dir.join(file_name);
+        fs::write(&path, content)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+pub fn docs_command() -> Command {
+    Command::new("generate-docs")
+        .about("Write man pages or a Markdown/HTML command reference")
+        .hide(true)
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_parser(value_parser!(DocFormat))
+                .default_value("markdown"),
+        )
+        .arg(Arg::new("out-dir").long("out-dir").default_value("docs"))
+}
+
+// Handles the hidden `generate-docs` subcommand, returning its exit code
+pub fn run_docs_command(cli_config: &CliConfig, matches: &ArgMatches, io: &Io) -> i32 {
+    let format = *matches.get_one::<DocFormat>("format").unwrap();
+    let out_dir = matches.get_one::<String>("out-dir").unwrap();
+
+    match generate_docs(cli_config, format, Path::new(out_dir)) {
+        Ok(paths) => {
+            for path in paths {
+                let _ = writeln!(io.stdout(), "{}", path.display());
+            }
+            0
+        }
+        Err(err) => {
+            let _ = writeln!(io.stderr(), "Could not generate docs: {}", err);
+            1
+        }
+    }
+}
+
+fn command_docs(cli_config: &CliConfig) -> Vec<CommandDoc> {
+    let mut app = build_cli(cli_config).disable_help_subcommand(true);
+    app.build();
+
+    visible_commands(&app)
+        .into_iter()
+        .enumerate()
+        .map(|(i, cmd)| {
+            let path = cmd
+                .get_bin_name()
+                .unwrap_or_else(|| cmd.get_name())
+                .to_string();
+            let usage = cmd.clone().render_usage().to_string();
+            CommandDoc {
+                about: cmd
+                    .get_long_about()
+                    .or_else(|| cmd.get_about())
+                    .map(|s| s.to_string())
+                    .unwrap_or_default(),
+                usage: usage.trim_start_matches("Usage: ").to_string(),
+                args: cmd
+                    .get_arguments()
+                    .filter(|arg| !arg.is_hide_set() && !is_builtin_flag(arg))
+                    // Global args are only listed for the app itself
+                    .filter(|arg| i == 0 || !arg.is_global_set())
+                    .map(arg_doc)
+                    .collect(),
+                subcommands: cmd
+                    .get_subcommands()
+                    .filter(|sub| !sub.is_hide_set())
+                    .map(|sub| {
+                        let sub_path = format!("{} {}", path, sub.get_name());
+                        let about = sub.get_about().map(|s| s.to_string()).unwrap_or_default();
+                        (sub_path, about)
+                    })
+                    .collect(),
+                examples: cmd.get_after_long_help().map(|s| s.to_string()),
+                path,
+            }
+        })
+        .collect()
+}
+
+fn arg_doc(arg: &Arg) -> ArgDoc {
+    let name = match (arg.get_long(), arg.get_short()) {
+        (Some(long), Some(short)) => format!("-{}, --{}", short, long),
+        (Some(long), None) => format!("--{}", long),
+        (None, Some(short)) => format!("-{}", short),
+        (None, None) => format!("<{}>", arg.get_id()),
+    };
+    // Flags have an implicit `false` default that is not worth listing
+    let takes_values = arg.get_num_args().is_some_and(|n| n.takes_values());
+    let default = arg
+        .get_default_values()
+        .iter()
+        .filter(|_| takes_values)
+        .map(|value| value.to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+
+    ArgDoc {
+        name,
+        help: arg.get_help().map(|s| s.to_string()).unwrap_or_default(),
+        env: arg.get_env().map(|env| env.to_string_lossy().into_owned()),
+        default: (!default.is_empty()).then(|| default.join(", ")),
+        required: arg.is_required_set(),
+    }
+}
+
+// The app followed by all visible subcommands, depth first
+fn visible_commands(cmd: &Command) -> Vec<&Command> {
+    let mut commands = vec![cmd];
+    for sub in cmd.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        commands.extend(visible_commands(sub));
+    }
+    commands
+}
+
+fn is_builtin_flag(arg: &Arg) -> bool {
+    matches!(arg.get_id().as_str(), "help" | "version")
+}
+
+fn code(value: &str) -> String {
+    format!("`{}`", value)
+}
+
+fn anchor(path: &str) -> String {
+    path.to_lowercase().replace(' ', "-")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ArgConfig, CommandConfig};
+    use std::sync::Arc;
+
+    fn get_test_cli_config() -> CliConfig<'static> {
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        cli_config.set_env_prefix("TEST_APP_");
+        let mut command = CommandConfig::new_standard("greet", "Greet someone", Arc::new(|_| {}));
+        command
+            .add_arg(
+                ArgConfig::new("name", "Name to greet")
+                    .long("name")
+                    .env("NAME"),
+            )
+            .add_example("test_app greet --name Ferris");
+        cli_config.add_command(command);
+        cli_config
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let markdown = render_markdown(&get_test_cli_config());
+
+        assert!(markdown.starts_with("# `test_app`"));
+        assert!(markdown.contains("## `test_app greet`"));
+        assert!(markdown.contains("| `--name` | Name to greet | `TEST_APP_NAME` |  |"));
+        assert!(markdown.contains("test_app greet --name Ferris"));
+        assert!(!markdown.contains("generate-docs"));
+    }
+
+    #[test]
+    fn test_render_man_pages() {
+        let pages = render_man_pages(&get_test_cli_config()).unwrap();
+        let names: Vec<_> = pages.iter().map(|(name, _)| name.as_str()).collect();
+
+        assert!(names.contains(&"test_app.1"));
+        assert!(names.contains(&"test_app-greet.1"));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::config::{CliConfig, CommandConfig};
+use crate::ui::Theme;
+use clap::builder::styling::{AnsiColor, Color as AnsiStyleColor, RgbColor, Style, Styles};
+use clap::Command;
+use crossterm::style::Color;
+
+/// Help colors from a theme, matching the widgets.
+pub fn styles(theme: &Theme) -> Styles {
+    Styles::styled()
+        .header(Style::new().bold().fg_color(to_ansi_color(theme.primary)))
+        .usage(Style::new().bold().fg_color(to_ansi_color(theme.primary)))
+        .literal(Style::new().bold().fg_color(to_ansi_color(theme.accent)))
+        .placeholder(Style::new().fg_color(to_ansi_color(theme.muted)))
+        .valid(Style::new().fg_color(to_ansi_color(theme.accent)))
+        .invalid(Style::new().bold().fg_color(to_ansi_color(theme.warning)))
+        .error(Style::new().bold().fg_color(to_ansi_color(theme.error)))
+}
+
+pub(crate) fn to_ansi_color(color: Color) -> Option<AnsiStyleColor> {
+    let color = match color {
+        Color::Rgb { r, g, b } => RgbColor(r, g, b).into(),
+        Color::Black => AnsiColor::Black.into(),
+        Color::DarkRed => AnsiColor::Red.into(),
+        Color::DarkGreen => AnsiColor::Green.into(),
+        Color::DarkYellow => AnsiColor::Yellow.into(),
+        Color::DarkBlue => AnsiColor::Blue.into(),
+        Color::DarkMagenta => AnsiColor::Magenta.into(),
+        Color::DarkCyan => AnsiColor::Cyan.into(),
+        Color::Grey => AnsiColor::White.into(),
+        Color::DarkGrey => AnsiColor::BrightBlack.into(),
+        Color::Red => AnsiColor::BrightRed.into(),
+        Color::Green => AnsiColor::BrightGreen.into(),
+        Color::Yellow => AnsiColor::BrightYellow.into(),
+        Color::Blue => AnsiColor::BrightBlue.into(),
+        Color::Magenta => AnsiColor::BrightMagenta.into(),
+        Color::Cyan => AnsiColor::BrightCyan.into(),
+        Color::White => AnsiColor::BrightWhite.into(),
+        Color::AnsiValue(value) => AnsiStyleColor::Ansi256(value.into()),
+        Color::Reset => return None,
+    };
+    Some(color)
+}
+
+// Examples and see-also links shown after `<command> --help`
+pub(crate) fn after_help(cmd: &CommandConfig) -> Option<String> {
+    let mut sections = Vec::new();
+    if !cmd.examples.is_empty() {
+        let examples: Vec<String> = cmd.examples.iter().map(|e| format!("  {}", e)).collect();
+        sections.push(format!("Examples:\n{}", examples.join("\n")));
+    }
+    if !cmd.see_also.is_empty() {
+        sections.push(format!("See also: {}", cmd.see_also.join(", ")));
+    }
+    (!sections.is_empty()).then(|| sections.join("\n\n"))
+}
+
+/// Replaces the flat command list of the app help with one section per
+/// command group, when any command has a group.
+pub(crate) fn apply_command_groups(cli_config: &CliConfig, app: Command) -> Command {
+    if cli_config.commands.iter().all(|cmd| cmd.group.is_none()) {
+        return app;
+    }
+
+    let styles = app.get_styles().clone();
+    let header = styles.get_header();
+    let literal = styles.get_literal();
+
+    // Built-in commands and ungrouped ones are listed first, like clap does
+    let mut sections: Vec<(String, Vec<(String, String)>)> = vec![("Commands".to_string(), vec![])];
+    for sub in app.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        let group = cli_config
+            .commands
+            .iter()
+            .find(|cmd| cmd.name == sub.get_name())
+            .and_then(|cmd| cmd.group.clone())
+            .unwrap_or_else(|| "Commands".to_string());
+        let name = sub.get_name().to_string();
+        let mut about = sub.get_about().map(|s| s.to_string()).unwrap_or_default();
+        // Same alias note as clap's own command list
+        let aliases: Vec<&str> = sub.get_visible_aliases().collect();
+        if !aliases.is_empty() {
+            let plural = if aliases.len() == 1 { "" } else { "es" };
+            about = format!("{} [alias{}: {}]", about, plural, aliases.join(", "));
+        }
+
+        match sections.iter_mut().find(|(heading, _)| *heading == group) {
+            Some((_, entries)) => entries.push((name, about)),
+            None => sections.push((group, vec![(name, about)])),
+        }
+    }
+    sections[0].1.push((
+        "help".to_string(),
+        "Print this message or the help of the given subcommand(s)".to_string(),
+    ));
+
+    let width = sections
+        .iter()
+        .flat_map(|(_, entries)| entries.iter().map(|(name, _)| name.len()))
+        .max()
+        .unwrap_or_default();
+
+    let mut commands = String::new();
+    for (heading, entries) in sections.iter().filter(|(_, entries)| !entries.is_empty()) {
+        commands.push_str(&format!(
+            "{}{}:{}\n",
+            header.render(),
+            heading,
+            header.render_reset()
+        ));
+        for (name, about) in entries {
+            commands.push_str(&format!(
+                "  {}{}{}{}  {}\n",
+                literal.render(),
+                name,
+                literal.render_reset(),
+                " ".repeat(width - name.len()),
+                about
+            ));
+        }
+        commands.push('\n');
+    }
+
+    // clap renders the arg sections, including custom headings, once the
+    // commands are hidden from it
+    let mut args_only = app.clone().help_template("{all-args}");
+    let names: Vec<String> = app
+        .get_subcommands()
+        .map(|sub| sub.get_name().to_string())
+        .collect();
+    for name in names {
+        args_only = args_only.mut_subcommand(name, |sub| sub.hide(true));
+    }
+    let args = args_only
+        .disable_help_subcommand(true)
+        .render_help()
+        .ansi()
+        .to_string();
+
+    let template = format!(
+        "{{before-help}}{{about-with-newline}}\n{{usage-heading}} {{usage}}\n\n{}{}\n{{after-help}}",
+        commands,
+        args.trim_end()
+    );
+    app.help_template(template)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli_builder::build_cli;
+    use crate::config::ArgConfig;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_help_colors_follow_the_theme() {
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        let nord = Theme::preset("nord").unwrap();
+        cli_config.set_theme(nord.clone());
+
+        let app = build_cli(&cli_config);
+        let styles = app.get_styles();
+        assert_eq!(
+            styles.get_header().get_fg_color(),
+            to_ansi_color(nord.primary)
+        );
+        assert_eq!(
+            styles.get_literal().get_fg_color(),
+            to_ansi_color(nord.accent)
+        );
+    }
+
+    #[test]
+    fn test_command_groups_in_help() {
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        let mut deploy = CommandConfig::new_standard("deploy", "Deploy the app", Arc::new(|_| {}));
+        deploy.set_group("Operations").add_alias("ship");
+        cli_config.add_command(deploy);
+        cli_config.add_command(CommandConfig::new_standard(
+            "status",
+            "Show the status",
+            Arc::new(|_| {}),
+        ));
+
+        let help = build_cli(&cli_config).render_help().to_string();
+
+        let commands = help.find("Commands:").unwrap();
+        let operations = help.find("Operations:").unwrap();
+        assert!(commands < help.find("status").unwrap());
+        assert!(operations < help.find("deploy").unwrap());
+        assert!(help.contains("Deploy the app [alias: ship]"));
+        assert!(help.find("status").unwrap() < operations);
+    }
+
+    #[test]
+    fn test_command_help_sections() {
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        let mut deploy = CommandConfig::new_standard("deploy", "Deploy the app", Arc::new(|_| {}));
+        deploy
+            .set_long_description("Deploy the app to every configured region.")
+            .add_example("test_app deploy --region eu")
+            .add_see_also("rollback")
+            .add_arg(
+                ArgConfig::new("region", "Target region")
+                    .long("region")
+                    .help_heading("Targeting"),
+            )
+            .add_arg(
+                ArgConfig::new("trace", "Debug output")
+                    .long("trace")
+                    .hidden(true),
+            );
+        cli_config.add_command(deploy);
+
+        let mut app = build_cli(&cli_config);
+        let help = app
+            .find_subcommand_mut("deploy")
+            .unwrap()
+            .render_long_help()
+            .to_string();
+
+        assert!(help.contains("Deploy the app to every configured region."));
+        assert!(help.contains("Targeting:"));
+        assert!(help.contains("Examples:\n  test_app deploy --region eu"));
+        assert!(help.contains("See also: rollback"));
+        assert!(!help.contains("--trace"));
+    }
+}
@@ -0,0 +1,166 @@
+use std::io;
+use std::sync::Arc;
+
+use crate::config::{CliConfig, CommandConfig};
+use crate::context::Context;
+
+/// Runs before or after an action with its context. An error from a pre-run
+/// hook stops the command before its action runs.
+pub type Hook<'a> = Arc<dyn Fn(&Context) -> io::Result<()> + Send + Sync + 'a>;
+
+/// Called with the error of a failed pre-run hook, middleware or action.
+pub type ErrorHook<'a> = Arc<dyn Fn(&Context, &io::Error) + Send + Sync + 'a>;
+
+/// Called once the command is done, whether it failed or not.
+pub type FinallyHook<'a> = Arc<dyn Fn(&Context) + Send + Sync + 'a>;
+
+/// The rest of the middleware chain, ending with the action itself.
+pub type Next<'n> = &'n dyn Fn(&Context) -> io::Result<()>;
+
+/// Wraps every action. Call `next` to continue, possibly with a changed
+/// context, or return without calling it to short-circuit the command.
+pub type Middleware<'a> = Arc<dyn Fn(&Context, Next) -> io::Result<()> + Send + Sync + 'a>;
+
+#[derive(Clone, Default)]
+pub struct Hooks<'a> {
+    pub pre_run: Vec<Hook<'a>>,
+    pub post_run: Vec<Hook<'a>>,
+    pub on_error: Vec<ErrorHook<'a>>,
+    pub finally: Vec<FinallyHook<'a>>,
+}
+
+impl<'a> Hooks<'a> {
+    pub fn new() -> Self {
+        Hooks::default()
+    }
+}
+
+// App hooks wrap command hooks: app pre-run, command pre-run, middleware and
+// action, command post-run, app post-run. Error and finally hooks run command
+// first, then app.
+pub(crate) fn run_command(
+    cli_config: &CliConfig,
+    cmd: &CommandConfig,
+    ctx: &Context,
+) -> io::Result<()> {
+    let layers = [&cli_config.hooks, &cmd.hooks];
+
+    let result = (|| {
+        for hook in layers.iter().flat_map(|hooks| hooks.pre_run.iter()) {
+            hook(ctx)?;
+        }
+        let action = |ctx: &Context| cmd.run_action_on(ctx, cli_config.runtime.as_ref());
+        run_chain(&cli_config.middleware, ctx, &action)?;
+        for hook in layers.iter().rev().flat_map(|hooks| hooks.post_run.iter()) {
+            hook(ctx)?;
+        }
+        Ok(())
+    })();
+
+    if let Err(err) = &result {
+        for hook in layers.iter().rev().flat_map(|hooks| hooks.on_error.iter()) {
+            hook(ctx, err);
+        }
+    }
+    for hook in layers.iter().rev().flat_map(|hooks| hooks.finally.iter()) {
+        hook(ctx);
+    }
+    result
+}
+
+fn run_chain(middleware: &[Middleware], ctx: &Context, action: Next) -> io::Result<()> {
+    match middleware.split_first() {
+        Some((first, rest)) => first(ctx, &|ctx| run_chain(rest, ctx, action)),
+        None => action(ctx),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Args;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_hook_and_middleware_order() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let log = |label: &'static str| {
+            let calls = calls.clone();
+            Arc::new(move |_: &Context| {
+                calls.lock().unwrap().push(label.to_string());
+                Ok(())
+            })
+        };
+
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        cli_config
+            .add_pre_run(log("app pre"))
+            .add_post_run(log("app post"))
+            .add_middleware({
+                let calls = calls.clone();
+                Arc::new(move |ctx, next| {
+                    let mut ctx = ctx.clone();
+                    ctx.insert_extension("ferris");
+                    calls.lock().unwrap().push("middleware".to_string());
+                    next(&ctx)
+                })
+            });
+        let action_calls = calls.clone();
+        let mut cmd = CommandConfig::new_standard(
+            "deploy",
+            "Deploy",
+            Arc::new(move |ctx| {
+                let user = ctx.extension::<&str>().unwrap();
+                action_calls
+                    .lock()
+                    .unwrap()
+                    .push(format!("action {}", user));
+            }),
+        );
+        cmd.add_pre_run(log("cmd pre"))
+            .add_post_run(log("cmd post"));
+
+        run_command(&cli_config, &cmd, &Context::new("deploy", Args::new())).unwrap();
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                "app pre",
+                "cmd pre",
+                "middleware",
+                "action ferris",
+                "cmd post",
+                "app post"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_pre_run_skips_action() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        let (errors, finals) = (calls.clone(), calls.clone());
+        cli_config
+            .add_pre_run(Arc::new(|_| Err(io::Error::other("not logged in"))))
+            .add_on_error(Arc::new(move |_, err| {
+                errors.lock().unwrap().push(err.to_string());
+            }))
+            .add_finally(Arc::new(move |ctx| {
+                finals
+                    .lock()
+                    .unwrap()
+                    .push(format!("finally {}", ctx.command));
+            }));
+        let action_calls = calls.clone();
+        let cmd = CommandConfig::new_standard(
+            "deploy",
+            "Deploy",
+            Arc::new(move |_| action_calls.lock().unwrap().push("action".to_string())),
+        );
+
+        assert!(run_command(&cli_config, &cmd, &Context::new("deploy", Args::new())).is_err());
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec!["not logged in", "finally deploy"]
+        );
+    }
+}
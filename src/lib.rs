@@ -1,3 +1,15 @@
 pub mod cli_builder;
+pub mod completions;
 pub mod config;
+pub mod context;
+pub mod docs;
+pub mod help;
+pub mod hooks;
+pub mod output;
+pub mod runtime;
+pub mod settings;
+pub mod signals;
+pub mod spec;
+pub mod testing;
+pub mod ui;
 pub mod wrappers;
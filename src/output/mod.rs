@@ -0,0 +1,215 @@
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use crossterm::queue;
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor};
+
+use crate::context::{GlobalFlags, Io};
+use crate::ui::{self, Capabilities, ColorSupport};
+
+/// How much a message matters, which decides whether it shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Success,
+    Info,
+    /// Shown with `-v`
+    Debug,
+    /// Shown with `-vv`
+    Trace,
+}
+
+impl Level {
+    /// Errors always show, `--quiet` hides everything else.
+    pub fn enabled(self, flags: &GlobalFlags) -> bool {
+        match self {
+            Level::Error => true,
+            _ if flags.quiet => false,
+            Level::Warn | Level::Success | Level::Info => true,
+            Level::Debug => flags.verbose >= 1,
+            Level::Trace => flags.verbose >= 2,
+        }
+    }
+
+    // The symbol before the message and its color in the theme
+    fn symbol(self, theme: &ui::Theme) -> (&'static str, Color) {
+        match self {
+            Level::Error => ("✗", theme.error),
+            Level::Warn => ("!", theme.warning),
+            Level::Success => ("✓", theme.success),
+            Level::Info => ("•", theme.accent),
+            Level::Debug | Level::Trace => ("·", theme.muted),
+        }
+    }
+}
+
+/// Writes a message to the stderr of `io`, unless the flags hide its level.
+///
+/// Messages are styled with the theme, as far as the terminal supports it,
+/// and land above the live view of the backend, like a spinner.
+pub fn emit(io: &Io, flags: &GlobalFlags, level: Level, message: impl Display) {
+    if !level.enabled(flags) {
+        return;
+    }
+    let theme = ui::theme();
+    let (symbol, color) = level.symbol(&theme);
+    let text = format!(" {}", message);
+    let capabilities = Capabilities::detect(io.terminal().stderr_tty);
+
+    let mut stderr = io.stderr();
+    let mut write = || -> io::Result<()> {
+        write_styled(&mut stderr, symbol, Some(color), true, capabilities)?;
+        let muted = (level >= Level::Debug).then_some(theme.muted);
+        write_styled(&mut stderr, &text, muted, false, capabilities)?;
+        writeln!(stderr)?;
+        stderr.flush()
+    };
+    // Like `eprintln!`, a message that can't be written is dropped
+    let backend = ui::backend();
+    let _ = backend.lock().unwrap().suspend(&mut write);
+}
+
+// Writes `text` in `color`, plain when the terminal shows no colors
+fn write_styled(
+    out: &mut impl Write,
+    text: &str,
+    color: Option<Color>,
+    bold: bool,
+    capabilities: Capabilities,
+) -> io::Result<()> {
+    let text = capabilities.text(text);
+    if capabilities.colors == ColorSupport::None {
+        return queue!(out, Print(text));
+    }
+    if let Some(color) = color.and_then(|color| capabilities.color(color)) {
+        queue!(out, SetForegroundColor(color))?;
+    }
+    if bold {
+        queue!(out, SetAttribute(Attribute::Bold))?;
+    }
+    queue!(out, Print(text), SetAttribute(Attribute::Reset), ResetColor)
+}
+
+#[cfg(feature = "log")]
+mod logger {
+    use log::{Log, Metadata, Record};
+
+    use super::{emit, Level};
+    use crate::context::Io;
+    use crate::wrappers::global_flags;
+
+    /// Shows the records of the `log` crate like `ctx.info` and friends
+    /// show messages, filtered by `--quiet` and `-v`.
+    pub struct Logger;
+
+    static LOGGER: Logger = Logger;
+
+    /// Makes `Logger` the logger of the process.
+    pub fn init_logger() -> Result<(), log::SetLoggerError> {
+        log::set_logger(&LOGGER)?;
+        log::set_max_level(log::LevelFilter::Trace);
+        Ok(())
+    }
+
+    fn level(level: log::Level) -> Level {
+        match level {
+            log::Level::Error => Level::Error,
+            log::Level::Warn => Level::Warn,
+            log::Level::Info => Level::Info,
+            log::Level::Debug => Level::Debug,
+            log::Level::Trace => Level::Trace,
+        }
+    }
+
+    impl Log for Logger {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            level(metadata.level()).enabled(&global_flags())
+        }
+
+        fn log(&self, record: &Record) {
+            emit(
+                &Io::std(),
+                &global_flags(),
+                level(record.level()),
+                record.args(),
+            );
+        }
+
+        fn flush(&self) {}
+    }
+}
+
+#[cfg(feature = "log")]
+pub use logger::{init_logger, Logger};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::block_on;
+    use crate::ui::CrosstermBackend;
+    use crate::wrappers::spin_while;
+    use std::sync::{Arc, Mutex};
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    #[test]
+    fn test_levels_follow_quiet_and_verbose() {
+        let flags = |quiet, verbose| GlobalFlags {
+            quiet,
+            verbose,
+            ..Default::default()
+        };
+        assert!(Level::Info.enabled(&flags(false, 0)));
+        assert!(!Level::Debug.enabled(&flags(false, 0)));
+        assert!(Level::Debug.enabled(&flags(false, 1)));
+        assert!(!Level::Trace.enabled(&flags(false, 1)));
+        assert!(!Level::Warn.enabled(&flags(true, 2)));
+        assert!(Level::Error.enabled(&flags(true, 0)));
+    }
+
+    #[test]
+    fn test_messages_print_above_a_spinner() {
+        let (io, _, stderr) = Io::null().capture();
+        let monochrome = Capabilities {
+            colors: ColorSupport::None,
+            unicode: false,
+        };
+        let backend = CrosstermBackend::new(io.stderr()).capabilities(monochrome);
+        let previous = ui::replace_local_backend(Some(Arc::new(Mutex::new(backend))));
+        let flags = GlobalFlags::default();
+        block_on(spin_while("Copying", async {
+            sleep(Duration::from_millis(100));
+            emit(&io, &flags, Level::Info, "Halfway");
+            sleep(Duration::from_millis(100));
+        }));
+        ui::replace_local_backend(previous);
+
+        // The spinner is taken off its line for the message, then drawn
+        // again below it
+        let stderr = String::from_utf8(stderr.lock().unwrap().clone()).unwrap();
+        let (before, after) = stderr.split_once(" Halfway\n").unwrap();
+        assert!(before.contains(" Copying"));
+        assert!(before.rfind("\x1b[J") > before.rfind(" Copying"));
+        assert!(after.contains(" Copying"));
+    }
+
+    #[test]
+    fn test_messages_go_to_stderr() {
+        let (io, stdout, stderr) = Io::null().capture();
+        let quiet = GlobalFlags {
+            quiet: true,
+            ..Default::default()
+        };
+        emit(&io, &GlobalFlags::default(), Level::Info, "Uploading");
+        emit(&io, &quiet, Level::Warn, "Hidden");
+        emit(&io, &quiet, Level::Error, "Failed");
+
+        let stderr = String::from_utf8(stderr.lock().unwrap().clone()).unwrap();
+        let lines: Vec<_> = stderr.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(" Uploading"));
+        assert!(lines[1].ends_with(" Failed"));
+        assert!(stdout.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use std::future::Future;
+use std::io;
+use std::pin::{pin, Pin};
+use std::sync::{Arc, OnceLock};
+use std::task::{Context as TaskContext, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use crate::context::Context;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// An action returning a future, created with `CommandConfig::new_async`.
+pub type AsyncAction<'a> = Arc<dyn Fn(Context) -> BoxFuture<'a, io::Result<()>> + Send + Sync + 'a>;
+
+/// Drives async actions to completion.
+///
+/// With the `tokio` feature, tokio runtimes and handles implement it and a
+/// multi-threaded tokio runtime is used by default. Without it, futures run on
+/// the calling thread with `BlockingRuntime`.
+pub trait Runtime: Send + Sync {
+    fn block_on(&self, future: BoxFuture<'_, io::Result<()>>) -> io::Result<()>;
+}
+
+/// Polls futures on the calling thread. Enough for futures that don't rely
+/// on an IO reactor, like ones from `std` or a thread pool.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BlockingRuntime;
+
+impl Runtime for BlockingRuntime {
+    fn block_on(&self, future: BoxFuture<'_, io::Result<()>>) -> io::Result<()> {
+        block_on(future)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Runtime for tokio::runtime::Runtime {
+    fn block_on(&self, future: BoxFuture<'_, io::Result<()>>) -> io::Result<()> {
+        tokio::runtime::Runtime::block_on(self, future)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Runtime for tokio::runtime::Handle {
+    fn block_on(&self, future: BoxFuture<'_, io::Result<()>>) -> io::Result<()> {
+        tokio::runtime::Handle::block_on(self, future)
+    }
+}
+
+// Used when the app did not set a runtime. Built once and shared by every
+// async action of the process.
+pub(crate) fn default_runtime() -> io::Result<Arc<dyn Runtime>> {
+    static DEFAULT: OnceLock<Arc<dyn Runtime>> = OnceLock::new();
+    if let Some(runtime) = DEFAULT.get() {
+        return Ok(runtime.clone());
+    }
+
+    #[cfg(feature = "tokio")]
+    let runtime: Arc<dyn Runtime> = Arc::new(
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()?,
+    );
+    #[cfg(not(feature = "tokio"))]
+    let runtime: Arc<dyn Runtime> = Arc::new(BlockingRuntime);
+    Ok(DEFAULT.get_or_init(|| runtime).clone())
+}
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs a future to completion on the current thread.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = TaskContext::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Args, CliConfig, CommandConfig};
+    use crate::context::Io;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_async_action_runs_to_completion() {
+        let greeting = Arc::new(Mutex::new(None));
+        let result = greeting.clone();
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        cli_config.add_command(CommandConfig::new_async(
+            "greet",
+            "Greet someone",
+            move |ctx: Context| {
+                let result = result.clone();
+                async move {
+                    let name = ctx.get("name").unwrap_or("world").to_string();
+                    // A future that completes from another thread
+                    let (sender, receiver) = std::sync::mpsc::channel();
+                    thread::spawn(move || sender.send(format!("Hello, {}!", name)));
+                    *result.lock().unwrap() = Some(receiver.recv().unwrap());
+                    Ok(())
+                }
+            },
+        ));
+
+        let mut args = Args::new();
+        args.insert("name", "Ferris");
+        let ctx = Context::new("greet", args);
+        crate::hooks::run_command(&cli_config, &cli_config.commands()[0], &ctx).unwrap();
+
+        assert_eq!(greeting.lock().unwrap().as_deref(), Some("Hello, Ferris!"));
+    }
+
+    // Counts the futures it drives
+    struct CountingRuntime(Mutex<usize>);
+
+    impl Runtime for CountingRuntime {
+        fn block_on(&self, future: BoxFuture<'_, io::Result<()>>) -> io::Result<()> {
+            *self.0.lock().unwrap() += 1;
+            block_on(future)
+        }
+    }
+
+    #[test]
+    fn test_async_action_uses_configured_runtime() {
+        let runtime = Arc::new(CountingRuntime(Mutex::new(0)));
+        let mut cli_config = CliConfig::new("test_app", "1.0", "A test application");
+        cli_config
+            .set_runtime(runtime.clone())
+            .add_command(CommandConfig::new_async("wait", "Wait", |_| async {
+                Ok(())
+            }));
+
+        let outcome = crate::cli_builder::run_from(&cli_config, ["test_app", "wait"], Io::null());
+
+        assert!(outcome.success());
+        assert_eq!(*runtime.0.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_default_runtime_is_built_once() {
+        let first = default_runtime().unwrap();
+        let second = default_runtime().unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+    }
+}
@@ -0,0 +1,303 @@
+use std::io;
+
+use serde_json::{Map, Value};
+use toml_edit::{DocumentMut, InlineTable, Item, Table};
+
+use super::{holds_a_table, holds_a_value, invalid_data, Format};
+
+/// Sets `key` in the content of a config file and returns the new content.
+///
+/// The rest of the document stays as it was, comments included. The value is
+/// read as the format reads values, so `3`, `true` and `[1, 2]` are a number,
+/// a boolean and an array, and anything else is a string.
+pub(super) fn set(content: &str, format: Format, key: &str, value: &str) -> io::Result<String> {
+    let parts: Vec<&str> = key.split('.').collect();
+    match format {
+        Format::Toml => set_toml(content, &parts, value),
+        Format::Json => set_json(content, &parts, value),
+        Format::Yaml => set_yaml(content, &parts, value),
+    }
+}
+
+fn set_toml(content: &str, parts: &[&str], value: &str) -> io::Result<String> {
+    let mut document: DocumentMut = content.parse().map_err(invalid_data)?;
+    let value = value
+        .parse::<toml_edit::Value>()
+        .ok()
+        .filter(|value| !value.is_inline_table())
+        .unwrap_or_else(|| value.into());
+
+    let (last, parents) = parts.split_last().unwrap();
+    let mut table: &mut dyn toml_edit::TableLike = document.as_table_mut();
+    let mut inline = false;
+    for (depth, part) in parents.iter().enumerate() {
+        // Tables made here only show up once they hold a value
+        let item = table.entry(part).or_insert_with(|| {
+            if inline {
+                Item::Value(InlineTable::new().into())
+            } else {
+                let mut table = Table::new();
+                table.set_implicit(true);
+                Item::Table(table)
+            }
+        });
+        inline = item.is_inline_table();
+        table = item
+            .as_table_like_mut()
+            .ok_or_else(|| holds_a_value(&parts[..=depth].join(".")))?;
+    }
+
+    match table.get_mut(last) {
+        Some(item) if item.is_table_like() || item.is_array_of_tables() => {
+            return Err(holds_a_table(&parts.join(".")));
+        }
+        // Keeps the comment after the old value
+        Some(Item::Value(old)) => {
+            let decor = old.decor().clone();
+            *old = value;
+            *old.decor_mut() = decor;
+        }
+        _ => {
+            table.insert(last, Item::Value(value));
+        }
+    }
+    Ok(document.to_string())
+}
+
+fn set_json(content: &str, parts: &[&str], value: &str) -> io::Result<String> {
+    let mut root = if content.trim().is_empty() {
+        Value::Object(Map::new())
+    } else {
+        serde_json::from_str(content).map_err(invalid_data)?
+    };
+    let value = serde_json::from_str(value)
+        .ok()
+        .filter(|value: &Value| !value.is_object() && !value.is_null())
+        .unwrap_or_else(|| Value::String(value.to_string()));
+
+    let (last, parents) = parts.split_last().unwrap();
+    let mut table = root
+        .as_object_mut()
+        .ok_or_else(|| holds_a_value("the file"))?;
+    for (depth, part) in parents.iter().enumerate() {
+        let child = table
+            .entry(part.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        // Null keys aren't set, see `flatten`
+        if child.is_null() {
+            *child = Value::Object(Map::new());
+        }
+        table = child
+            .as_object_mut()
+            .ok_or_else(|| holds_a_value(&parts[..=depth].join(".")))?;
+    }
+    if table.get(*last).is_some_and(Value::is_object) {
+        return Err(holds_a_table(&parts.join(".")));
+    }
+    table.insert(last.to_string(), value);
+
+    let mut content = serde_json::to_string_pretty(&root).map_err(invalid_data)?;
+    content.push('\n');
+    Ok(content)
+}
+
+fn set_yaml(content: &str, parts: &[&str], value: &str) -> io::Result<String> {
+    use serde_yaml::{Mapping, Value};
+
+    let mut root = if content.trim().is_empty() {
+        Value::Mapping(Mapping::new())
+    } else {
+        serde_yaml::from_str(content).map_err(invalid_data)?
+    };
+    let value = serde_yaml::from_str(value)
+        .ok()
+        .filter(|value: &Value| value.is_number() || value.is_bool() || value.is_sequence())
+        .unwrap_or_else(|| Value::String(value.to_string()));
+
+    let (last, parents) = parts.split_last().unwrap();
+    let mut table = root
+        .as_mapping_mut()
+        .ok_or_else(|| holds_a_value("the file"))?;
+    for (depth, part) in parents.iter().enumerate() {
+        let child = table
+            .entry(Value::from(*part))
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        if child.is_null() {
+            *child = Value::Mapping(Mapping::new());
+        }
+        table = child
+            .as_mapping_mut()
+            .ok_or_else(|| holds_a_value(&parts[..=depth].join(".")))?;
+    }
+    if table.get(*last).is_some_and(Value::is_mapping) {
+        return Err(holds_a_table(&parts.join(".")));
+    }
+    table.insert(Value::from(*last), value.clone());
+
+    // Editing the lines keeps comments, as long as they read back the same
+    let edited = edit_yaml_lines(content, parts, &value)
+        .filter(|edited| serde_yaml::from_str::<Value>(edited).ok().as_ref() == Some(&root));
+    match edited {
+        Some(edited) => Ok(edited),
+        None => serde_yaml::to_string(&root).map_err(invalid_data),
+    }
+}
+
+// Sets the value in a block style document by its lines. Returns `None` for
+// values that don't fit on the line of their key.
+fn edit_yaml_lines(content: &str, parts: &[&str], value: &serde_yaml::Value) -> Option<String> {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let (mut start, mut end) = (0, lines.len());
+    let mut parent_indent = None;
+    for (depth, part) in parts.iter().enumerate() {
+        let key = yaml_inline(&serde_yaml::Value::from(*part))?;
+        let indent = (start..end).find_map(|i| indent_of(&lines[i]));
+        let found = indent.and_then(|indent| {
+            (start..end).find(|&i| {
+                indent_of(&lines[i]) == Some(indent)
+                    && lines[i][indent..]
+                        .strip_prefix(&key)
+                        .is_some_and(|rest| rest.starts_with(':'))
+            })
+        });
+
+        let Some(line) = found else {
+            let indent = indent.unwrap_or_else(|| parent_indent.map_or(0, |indent| indent + 2));
+            // After the last line of the block, before comments trailing it
+            let at = (start..end)
+                .rev()
+                .find(|&i| indent_of(&lines[i]).is_some())
+                .map_or(start, |i| i + 1);
+            let mut added = Vec::new();
+            for (offset, part) in parts[depth..].iter().enumerate() {
+                let pad = " ".repeat(indent + 2 * offset);
+                let key = yaml_inline(&serde_yaml::Value::from(*part))?;
+                if depth + offset + 1 == parts.len() {
+                    added.push(format!("{}{}: {}", pad, key, yaml_inline(value)?));
+                } else {
+                    added.push(format!("{}{}:", pad, key));
+                }
+            }
+            lines.splice(at..at, added);
+            return Some(joined(lines));
+        };
+
+        let indent = indent.unwrap();
+        let block_end = block_end(&lines, line, end, indent);
+        if depth + 1 == parts.len() {
+            let rest = &lines[line][indent + key.len() + 1..];
+            let comment = rest.find(" #").map_or("", |at| &rest[at..]);
+            lines[line] = format!(
+                "{}{}: {}{}",
+                " ".repeat(indent),
+                key,
+                yaml_inline(value)?,
+                comment
+            );
+            lines.drain(line + 1..block_end);
+            return Some(joined(lines));
+        }
+        (start, end, parent_indent) = (line + 1, block_end, Some(indent));
+    }
+    None
+}
+
+// The indentation of a line holding a key or value, not of blank lines,
+// comments or document markers
+fn indent_of(line: &str) -> Option<usize> {
+    let trimmed = line.trim_start();
+    if trimmed.is_empty() || trimmed.starts_with('#') || line == "---" || line == "..." {
+        return None;
+    }
+    Some(line.len() - trimmed.len())
+}
+
+// The line after the last one nested under `line`
+fn block_end(lines: &[String], line: usize, end: usize, indent: usize) -> usize {
+    let mut last = line;
+    for (i, line) in lines.iter().enumerate().take(end).skip(line + 1) {
+        match indent_of(line) {
+            Some(nested) if nested > indent => last = i,
+            Some(_) => break,
+            None => {}
+        }
+    }
+    last + 1
+}
+
+// A value as it's written after a key, arrays in flow style
+fn yaml_inline(value: &serde_yaml::Value) -> Option<String> {
+    if let serde_yaml::Value::Sequence(items) = value {
+        let items: Option<Vec<String>> = items.iter().map(yaml_inline).collect();
+        return Some(format!("[{}]", items?.join(", ")));
+    }
+    let written = serde_yaml::to_string(value).ok()?;
+    let written = written.trim_end();
+    (!written.contains('\n')).then(|| written.to_string())
+}
+
+fn joined(lines: Vec<String>) -> String {
+    let mut content = lines.join("\n");
+    content.push('\n');
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_keeps_comments_and_types() {
+        let toml = "# Defaults\nname = \"Ferris\" # who to greet\n\n[greet]\ntimes = 1\n";
+        assert_eq!(
+            set(toml, Format::Toml, "greet.times", "3").unwrap(),
+            "# Defaults\nname = \"Ferris\" # who to greet\n\n[greet]\ntimes = 3\n"
+        );
+        assert_eq!(
+            set(toml, Format::Toml, "name", "Crab").unwrap(),
+            "# Defaults\nname = \"Crab\" # who to greet\n\n[greet]\ntimes = 1\n"
+        );
+        assert_eq!(
+            set("", Format::Toml, "greet.tags", "[\"a\", \"b\"]").unwrap(),
+            "[greet]\ntags = [\"a\", \"b\"]\n"
+        );
+
+        let yaml = "# Defaults\nname: Ferris # who to greet\ngreet:\n  times: 1\n\n# Later\n";
+        assert_eq!(
+            set(yaml, Format::Yaml, "greet.loud", "true").unwrap(),
+            "# Defaults\nname: Ferris # who to greet\ngreet:\n  times: 1\n  loud: true\n\n# Later\n"
+        );
+        assert_eq!(
+            set(yaml, Format::Yaml, "name", "3").unwrap(),
+            "# Defaults\nname: 3 # who to greet\ngreet:\n  times: 1\n\n# Later\n"
+        );
+
+        let json = set("{\"b\": 1, \"a\": {}}", Format::Json, "a.on", "true").unwrap();
+        assert_eq!(
+            json,
+            "{\n  \"b\": 1,\n  \"a\": {\n    \"on\": true\n  }\n}\n"
+        );
+        let json = set("", Format::Json, "name", "Ferris").unwrap();
+        assert_eq!(json, "{\n  \"name\": \"Ferris\"\n}\n");
+    }
+
+    #[test]
+    fn test_set_fails_on_tables_and_values() {
+        for (content, format) in [
+            ("name = \"x\"\n[greet]\ntimes = 1\n", Format::Toml),
+            ("{\"greet\": {\"times\": 1}, \"name\": \"x\"}", Format::Json),
+            ("greet:\n  times: 1\nname: x\n", Format::Yaml),
+        ] {
+            let err = set(content, format, "greet", "3").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "'greet' is a table of settings, not a value"
+            );
+            let err = set(content, format, "name.first", "3").unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "'name' is set to a value, not a table of settings"
+            );
+        }
+    }
+}
@@ -0,0 +1,388 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{Map, Value};
+
+use crate::context::Io;
+use crate::wrappers::edit_file;
+
+mod document;
+
+const EXTENSIONS: [&str; 4] = ["toml", "json", "yaml", "yml"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()? {
+            "toml" => Some(Format::Toml),
+            "json" => Some(Format::Json),
+            "yaml" | "yml" => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+}
+
+/// Settings merged from config files, used as defaults for args.
+///
+/// Keys are dotted paths: `name` applies to every arg called `name`, while
+/// `greet.name` only applies to the `name` arg of the `greet` command.
+#[derive(Clone, Debug, Default)]
+pub struct Settings {
+    values: BTreeMap<String, String>,
+    sources: Vec<PathBuf>,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Settings::default()
+    }
+
+    /// Loads and merges the system, user and project config files of an app,
+    /// later files overriding earlier ones. Missing files are skipped.
+    pub fn load(app_name: &str) -> io::Result<Settings> {
+        let mut settings = Settings::new();
+        for path in Settings::search_paths(app_name) {
+            if path.is_file() {
+                settings.merge(Settings::from_file(&path)?);
+            }
+        }
+        Ok(settings)
+    }
+
+    /// Every path `load` looks at, from lowest to highest priority.
+    pub fn search_paths(app_name: &str) -> Vec<PathBuf> {
+        let app = file_name(app_name);
+        let mut dirs = Vec::new();
+
+        if cfg!(unix) {
+            dirs.push(PathBuf::from("/etc").join(&app).join("config"));
+        }
+        if let Some(config_dir) = dirs::config_dir() {
+            dirs.push(config_dir.join(&app).join("config"));
+        }
+        dirs.push(PathBuf::from(format!(".{}", app)));
+
+        dirs.iter()
+            .flat_map(|base| EXTENSIONS.iter().map(move |ext| base.with_extension(ext)))
+            .collect()
+    }
+
+    /// The user config file of an app, which `config set` and `config edit`
+    /// write to. Prefers an existing file in any format, else `config.toml`.
+    pub fn user_path(app_name: &str) -> Option<PathBuf> {
+        let base = dirs::config_dir()?.join(file_name(app_name)).join("config");
+        let existing = EXTENSIONS
+            .iter()
+            .map(|ext| base.with_extension(ext))
+            .find(|path| path.is_file());
+        Some(existing.unwrap_or_else(|| base.with_extension("toml")))
+    }
+
+    pub fn from_file(path: &Path) -> io::Result<Settings> {
+        let format = Format::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unsupported config file: {}", path.display()),
+            )
+        })?;
+        let mut settings = Settings::parse(&fs::read_to_string(path)?, format)?;
+        settings.sources.push(path.to_path_buf());
+        Ok(settings)
+    }
+
+    pub fn parse(content: &str, format: Format) -> io::Result<Settings> {
+        let value: Value = match format {
+            Format::Toml => toml::from_str(content).map_err(invalid_data)?,
+            Format::Json => serde_json::from_str(content).map_err(invalid_data)?,
+            Format::Yaml => serde_yaml::from_str(content).map_err(invalid_data)?,
+        };
+
+        let mut settings = Settings::new();
+        flatten("", &value, &mut settings.values);
+        Ok(settings)
+    }
+
+    /// Serializes the settings as nested tables in the given format.
+    pub fn render(&self, format: Format) -> io::Result<String> {
+        let mut root = Map::new();
+        for (key, value) in self.values.iter() {
+            insert_nested(&mut root, key, key, value)?;
+        }
+        let root = Value::Object(root);
+
+        match format {
+            Format::Toml => toml::to_string_pretty(&root).map_err(invalid_data),
+            Format::Json => serde_json::to_string_pretty(&root).map_err(invalid_data),
+            Format::Yaml => serde_yaml::to_string(&root).map_err(invalid_data),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let format = Format::from_path(path).unwrap_or(Format::Toml);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, self.render(format)?)
+    }
+
+    pub fn merge(&mut self, other: Settings) {
+        self.values.extend(other.values);
+        self.sources.extend(other.sources);
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    pub fn set(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.values.insert(key.into(), value.into());
+    }
+
+    /// The default for an arg, preferring the command-specific key.
+    pub fn arg_default(&self, command: &str, arg: &str) -> Option<&str> {
+        self.get(&format!("{}.{}", command, arg))
+            .or_else(|| self.get(arg))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    pub fn sources(&self) -> &[PathBuf] {
+        &self.sources
+    }
+}
+
+// Handles the built-in `config` subcommand, returning its exit code
+pub fn run_config_command(
+    app_name: &str,
+    settings: &Settings,
+    matches: &clap::ArgMatches,
+    io: &Io,
+) -> i32 {
+    let (mut stdout, mut stderr) = (io.stdout(), io.stderr());
+    let result = match matches.subcommand() {
+        Some(("get", sub_matches)) => {
+            let key = sub_matches.get_one::<String>("key").unwrap();
+            match settings.get(key) {
+                Some(value) => {
+                    let _ = writeln!(stdout, "{}", value);
+                    Ok(())
+                }
+                None => Err(format!("Key '{}' is not set.", key)),
+            }
+        }
+        Some(("set", sub_matches)) => {
+            let key = sub_matches.get_one::<String>("key").unwrap();
+            let value = sub_matches.get_one::<String>("value").unwrap();
+            set_user_value(app_name, key, value)
+                .map_err(|err| format!("Could not save '{}': {}", key, err))
+        }
+        Some(("list", _)) => {
+            for (key, value) in settings.iter() {
+                let _ = writeln!(stdout, "{} = {}", key, value);
+            }
+            Ok(())
+        }
+        Some(("edit", _)) => edit_user_file(app_name)
+            .map_err(|err| format!("Could not edit the config file: {}", err)),
+        _ => {
+            for path in settings.sources() {
+                let _ = writeln!(stdout, "{}", path.display());
+            }
+            Ok(())
+        }
+    };
+    match result {
+        Ok(()) => 0,
+        Err(message) => {
+            let _ = writeln!(stderr, "{}", message);
+            1
+        }
+    }
+}
+
+pub fn config_command() -> clap::Command {
+    use clap::{Arg, Command};
+
+    Command::new("config")
+        .about("Get, set, list or edit configuration values")
+        .subcommand(
+            Command::new("get")
+                .about("Print a configuration value")
+                .arg(Arg::new("key").required(true)),
+        )
+        .subcommand(
+            Command::new("set")
+                .about("Save a value to the user config file")
+                .arg(Arg::new("key").required(true))
+                .arg(Arg::new("value").required(true)),
+        )
+        .subcommand(Command::new("list").about("List all configuration values"))
+        .subcommand(Command::new("edit").about("Open the user config file in $EDITOR"))
+}
+
+// Edits the user config file rather than rendering it again, so that its
+// comments and the types of its values stay
+fn set_user_value(app_name: &str, key: &str, value: &str) -> io::Result<()> {
+    let path = user_path_or_err(app_name)?;
+    let content = if path.is_file() {
+        fs::read_to_string(&path)?
+    } else {
+        String::new()
+    };
+    let format = Format::from_path(&path).unwrap_or(Format::Toml);
+    let content = document::set(&content, format, key, value)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content)
+}
+
+fn edit_user_file(app_name: &str) -> io::Result<()> {
+    let path = user_path_or_err(app_name)?;
+    if !path.is_file() {
+        Settings::new().save(&path)?;
+    }
+    edit_file(&path)
+}
+
+fn user_path_or_err(app_name: &str) -> io::Result<PathBuf> {
+    Settings::user_path(app_name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "No config directory for this platform",
+        )
+    })
+}
+
+// Turns an app name like "My CLI" into "my-cli" for use in paths
+pub(crate) fn file_name(app_name: &str) -> String {
+    app_name
+        .trim()
+        .to_lowercase()
+        .replace(|c: char| c.is_whitespace() || c == '/', "-")
+}
+
+fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&key, value, out);
+            }
+        }
+        Value::String(s) => {
+            out.insert(prefix.to_string(), s.clone());
+        }
+        Value::Null => {}
+        other => {
+            out.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+fn insert_nested(
+    root: &mut Map<String, Value>,
+    key: &str,
+    rest: &str,
+    value: &str,
+) -> io::Result<()> {
+    match rest.split_once('.') {
+        Some((head, rest)) => {
+            let child = root
+                .entry(head.to_string())
+                .or_insert_with(|| Value::Object(Map::new()));
+            let path = &key[..key.len() - rest.len() - 1];
+            match child {
+                Value::Object(child) => insert_nested(child, key, rest, value),
+                _ => Err(holds_a_value(path)),
+            }
+        }
+        None if root.get(rest).is_some_and(Value::is_object) => Err(holds_a_table(key)),
+        None => {
+            root.insert(rest.to_string(), Value::String(value.to_string()));
+            Ok(())
+        }
+    }
+}
+
+// A key can't be set under `path`, which holds a value
+fn holds_a_value(path: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("'{}' is set to a value, not a table of settings", path),
+    )
+}
+
+fn holds_a_table(key: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("'{}' is a table of settings, not a value", key),
+    )
+}
+
+fn invalid_data<E: std::error::Error + Send + Sync + 'static>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_formats_flatten_to_same_keys() {
+        let toml =
+            Settings::parse("name = \"Ferris\"\n[greet]\ntimes = 3\n", Format::Toml).unwrap();
+        let json =
+            Settings::parse(r#"{"name": "Ferris", "greet": {"times": 3}}"#, Format::Json).unwrap();
+        let yaml = Settings::parse("name: Ferris\ngreet:\n  times: 3\n", Format::Yaml).unwrap();
+
+        for settings in [toml, json, yaml] {
+            assert_eq!(settings.get("name"), Some("Ferris"));
+            assert_eq!(settings.get("greet.times"), Some("3"));
+        }
+    }
+
+    #[test]
+    fn test_merge_and_arg_default() {
+        let mut settings = Settings::parse("name = \"system\"", Format::Toml).unwrap();
+        settings.merge(Settings::parse("[greet]\nname = \"project\"", Format::Toml).unwrap());
+
+        assert_eq!(settings.arg_default("greet", "name"), Some("project"));
+        assert_eq!(settings.arg_default("other", "name"), Some("system"));
+        assert_eq!(settings.arg_default("other", "missing"), None);
+    }
+
+    #[test]
+    fn test_render_round_trip() {
+        let mut settings = Settings::new();
+        settings.set("name", "Ferris");
+        settings.set("greet.times", "3");
+
+        for format in [Format::Toml, Format::Json, Format::Yaml] {
+            let parsed = Settings::parse(&settings.render(format).unwrap(), format).unwrap();
+            assert_eq!(parsed.get("name"), Some("Ferris"));
+            assert_eq!(parsed.get("greet.times"), Some("3"));
+        }
+
+        settings.set("name.first", "Ferris");
+        let err = settings.render(Format::Toml).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "'name' is set to a value, not a table of settings"
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crossterm::cursor::Show;
+use crossterm::execute;
+use crossterm::style::ResetColor;
+use crossterm::terminal::disable_raw_mode;
+
+// Conventional exit status of a process stopped by SIGINT
+pub const CANCELLED_EXIT_CODE: i32 = 130;
+
+/// Set when the user asks the running command to stop. Long-running actions
+/// should check it and return `cancelled()` early.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+
+    /// Fails with `cancelled()` once cancelled, for use with `?`.
+    pub fn check(&self) -> io::Result<()> {
+        if self.is_cancelled() {
+            return Err(cancelled());
+        }
+        Ok(())
+    }
+}
+
+/// What `cancelled()` wraps, so that `is_cancelled` can tell a stopped
+/// command from one that failed with `ErrorKind::Interrupted`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("cancelled")
+    }
+}
+
+impl Error for Cancelled {}
+
+/// The error of a command the user stopped, reported apart from failures.
+pub fn cancelled() -> io::Error {
+    io::Error::other(Cancelled)
+}
+
+pub fn is_cancelled(err: &io::Error) -> bool {
+    err.get_ref().is_some_and(|inner| inner.is::<Cancelled>())
+}
+
+// The token of code that runs outside of a command
+static TOKEN: Mutex<Option<CancellationToken>> = Mutex::new(None);
+// Tokens of the runs going on, which may be several with `run_from`
+static RUNNING: Mutex<Vec<CancellationToken>> = Mutex::new(Vec::new());
+
+thread_local! {
+    // The token of the run on this thread, set by `start_run`
+    static LOCAL_TOKEN: RefCell<Option<CancellationToken>> = const { RefCell::new(None) };
+}
+
+/// The token cancelled by SIGINT and SIGTERM, the one of the run on this
+/// thread.
+pub fn cancellation_token() -> CancellationToken {
+    if let Some(token) = LOCAL_TOKEN.with(|local| local.borrow().clone()) {
+        return token;
+    }
+    TOKEN
+        .lock()
+        .unwrap()
+        .get_or_insert_with(CancellationToken::new)
+        .clone()
+}
+
+// Starts a run on this thread with a fresh token, so that a signal received
+// by an earlier run doesn't cancel this one
+pub(crate) fn start_run() -> CancellationToken {
+    let token = CancellationToken::new();
+    RUNNING.lock().unwrap().push(token.clone());
+    replace_local_token(Some(token.clone()));
+    token
+}
+
+// Ends the run of `token`, which signals no longer cancel
+pub(crate) fn end_run(token: &CancellationToken) {
+    RUNNING
+        .lock()
+        .unwrap()
+        .retain(|running| !Arc::ptr_eq(&running.0, &token.0));
+}
+
+pub(crate) fn replace_local_token(token: Option<CancellationToken>) -> Option<CancellationToken> {
+    LOCAL_TOKEN.with(|local| local.replace(token))
+}
+
+// Cancels every run, and code outside of them, returning whether any of them
+// was not cancelled yet
+fn cancel_all() -> bool {
+    let mut tokens = RUNNING.lock().unwrap().clone();
+    tokens.push(
+        TOKEN
+            .lock()
+            .unwrap()
+            .get_or_insert_with(CancellationToken::new)
+            .clone(),
+    );
+    let pending = tokens.iter().any(|token| !token.is_cancelled());
+    tokens.iter().for_each(CancellationToken::cancel);
+    pending
+}
+
+#[derive(Debug, PartialEq)]
+enum SignalAction {
+    Cancel,
+    Exit,
+}
+
+// A signal asks the commands to stop, another one while all of them are
+// still stopping gives up on them
+fn signal_action(pending: bool) -> SignalAction {
+    if pending {
+        SignalAction::Cancel
+    } else {
+        SignalAction::Exit
+    }
+}
+
+/// Installs the SIGINT/SIGTERM handler, once per process.
+///
+/// The first signal restores the terminal and cancels the tokens of the
+/// running commands. A second one, before they stopped, exits right away with
+/// status 130.
+pub fn install_handler() {
+    static INSTALLED: OnceLock<()> = OnceLock::new();
+    INSTALLED.get_or_init(|| {
+        // Another handler may already be installed by the app
+        let _ = ctrlc::set_handler(move || {
+            restore_terminal();
+            if signal_action(cancel_all()) == SignalAction::Exit {
+                process::exit(CANCELLED_EXIT_CODE);
+            }
+        });
+    });
+}
+
+/// Leaves raw mode and shows the cursor again, in case a widget was
+/// interrupted while drawing.
+pub fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let mut stdout = io::stdout();
+    if stdout.is_terminal() {
+        let _ = execute!(stdout, ResetColor, Show);
+        let _ = stdout.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        let shared = token.clone();
+        assert!(token.check().is_ok());
+
+        shared.cancel();
+        assert!(token.is_cancelled());
+        assert!(is_cancelled(&token.check().unwrap_err()));
+        assert!(!is_cancelled(&io::ErrorKind::Interrupted.into()));
+    }
+
+    #[test]
+    fn test_runs_start_with_fresh_token() {
+        let first = start_run();
+        first.cancel();
+
+        let second = start_run();
+        assert!(!second.is_cancelled());
+        assert!(!cancellation_token().is_cancelled());
+        end_run(&first);
+        end_run(&second);
+        replace_local_token(None);
+    }
+
+    #[test]
+    fn test_runs_on_two_threads_keep_their_token() {
+        let barrier = Arc::new(std::sync::Barrier::new(2));
+        let run = |cancel: bool| {
+            let barrier = barrier.clone();
+            std::thread::spawn(move || {
+                let token = start_run();
+                if cancel {
+                    token.cancel();
+                }
+                // Both runs started before either looks at its token
+                barrier.wait();
+                let cancelled = cancellation_token().is_cancelled();
+                end_run(&token);
+                cancelled
+            })
+        };
+        let (cancelled, kept) = (run(true), run(false));
+
+        assert!(cancelled.join().unwrap());
+        assert!(!kept.join().unwrap());
+    }
+
+    #[test]
+    fn test_second_signal_exits() {
+        assert_eq!(signal_action(true), SignalAction::Cancel);
+        assert_eq!(signal_action(false), SignalAction::Exit);
+    }
+}
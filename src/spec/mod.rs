@@ -0,0 +1,479 @@
+use std::collections::HashMap;
+use std::fs;
+use std::future::Future;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use rustubble::list::Item;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{
+    ArgConfig, ArgGroupConfig, CliConfig, CommandConfig, CommandType, StandardAction,
+    StructuredAction, UIAction, UIWithReturnAction,
+};
+use crate::context::Context;
+use crate::runtime::AsyncAction;
+use crate::settings::Format;
+use crate::wrappers::TextAreaOptions;
+
+/// A CLI described in a TOML, JSON or YAML file.
+///
+/// ```toml
+/// name = "mycli"
+/// version = "1.0"
+/// about = "A CLI defined in a spec file"
+///
+/// [[commands]]
+/// name = "greet"
+/// description = "Greet someone"
+/// action = "greet"
+/// args = [{ name = "name", help = "Name to greet", long = "name" }]
+///
+/// [[commands]]
+/// name = "color"
+/// description = "Pick a color"
+/// ui = { type = "menu_list", title = "Colors", items = ["red", "blue"] }
+/// ```
+///
+/// Commands either name an `action` registered in an `ActionRegistry`, or
+/// describe a `ui` element that is added with the matching `add_*` helper.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CliSpec {
+    pub name: String,
+    #[serde(default)]
+    pub version: String,
+    #[serde(default)]
+    pub about: String,
+    pub env_prefix: Option<String>,
+    #[serde(default)]
+    pub commands: Vec<CommandSpec>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandSpec {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub action: Option<String>,
+    pub ui: Option<UiSpec>,
+    #[serde(default)]
+    pub args: Vec<ArgSpec>,
+    #[serde(default)]
+    pub groups: Vec<GroupSpec>,
+    #[serde(default)]
+    pub examples: Vec<String>,
+    pub long_description: Option<String>,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub see_also: Vec<String>,
+    pub group: Option<String>,
+    #[serde(default)]
+    pub hidden: bool,
+    pub deprecated: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArgSpec {
+    pub name: String,
+    #[serde(default)]
+    pub help: String,
+    #[serde(default)]
+    pub required: bool,
+    pub long: Option<String>,
+    pub short: Option<char>,
+    pub env: Option<String>,
+    #[serde(default)]
+    pub show_env_value: bool,
+    pub default: Option<String>,
+    pub help_heading: Option<String>,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub flag: bool,
+    #[serde(default)]
+    pub conflicts_with: Vec<String>,
+    #[serde(default)]
+    pub requires: Vec<String>,
+    #[serde(default)]
+    pub required_unless_present: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupSpec {
+    pub name: String,
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub multiple: bool,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemSpec {
+    pub title: String,
+    #[serde(default)]
+    pub subtitle: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum UiSpec {
+    Input {
+        #[serde(default)]
+        placeholder: String,
+        #[serde(default)]
+        initial_text: String,
+        #[serde(default)]
+        label: String,
+    },
+    TextArea {
+        #[serde(default)]
+        label: String,
+        #[serde(default = "default_visible_lines")]
+        visible_lines: usize,
+        #[serde(default)]
+        initial_text: String,
+        max_length: Option<usize>,
+        max_lines: Option<usize>,
+    },
+    MenuList {
+        #[serde(default)]
+        title: String,
+        #[serde(default)]
+        subtitle: String,
+        items: Vec<String>,
+    },
+    ItemList {
+        #[serde(default)]
+        title: String,
+        items: Vec<ItemSpec>,
+    },
+    Table {
+        headers: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+    Loader {
+        text: String,
+        // The spinner of the theme when empty
+        #[serde(default)]
+        style: String,
+    },
+    Timer {
+        secs: u64,
+    },
+    Stopwatch,
+    Viewport {
+        file: String,
+    },
+}
+
+fn default_visible_lines() -> usize {
+    6
+}
+
+/// Actions that spec files can refer to by name.
+#[derive(Clone, Default)]
+pub struct ActionRegistry<'a> {
+    actions: HashMap<String, CommandType<'a>>,
+}
+
+impl<'a> ActionRegistry<'a> {
+    pub fn new() -> Self {
+        ActionRegistry {
+            actions: HashMap::new(),
+        }
+    }
+
+    pub fn register(&mut self, name: impl Into<String>, action: CommandType<'a>) -> &mut Self {
+        self.actions.insert(name.into(), action);
+        self
+    }
+
+    pub fn register_standard(
+        &mut self,
+        name: impl Into<String>,
+        action: StandardAction<'a>,
+    ) -> &mut Self {
+        self.register(name, CommandType::Standard(action))
+    }
+
+    pub fn register_ui(&mut self, name: impl Into<String>, action: UIAction<'a>) -> &mut Self {
+        self.register(name, CommandType::UI(action))
+    }
+
+    pub fn register_ui_with_return(
+        &mut self,
+        name: impl Into<String>,
+        action: UIWithReturnAction<'a>,
+    ) -> &mut Self {
+        self.register(name, CommandType::UIWithReturn(action))
+    }
+
+    pub fn register_async<F, Fut>(&mut self, name: impl Into<String>, action: F) -> &mut Self
+    where
+        F: Fn(Context) -> Fut + Send + Sync + 'a,
+        Fut: Future<Output = io::Result<()>> + Send + 'a,
+    {
+        let action: AsyncAction<'a> = Arc::new(move |ctx| Box::pin(action(ctx)));
+        self.register(name, CommandType::Async(action))
+    }
+
+    /// Registers an action returning a value, see `CommandConfig::new_structured`.
+    pub fn register_structured<F, T>(&mut self, name: impl Into<String>, action: F) -> &mut Self
+    where
+        F: Fn(&Context) -> io::Result<T> + Send + Sync + 'a,
+        T: Serialize,
+    {
+        let action: StructuredAction<'a> =
+            Arc::new(move |ctx| serde_json::to_value(action(ctx)?).map_err(io::Error::other));
+        self.register(name, CommandType::Structured(action))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CommandType<'a>> {
+        self.actions.get(name)
+    }
+}
+
+impl CliSpec {
+    pub fn from_file(path: &Path) -> io::Result<CliSpec> {
+        let format = Format::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unsupported spec file: {}", path.display()),
+            )
+        })?;
+        CliSpec::parse(&fs::read_to_string(path)?, format)
+    }
+
+    pub fn parse(content: &str, format: Format) -> io::Result<CliSpec> {
+        match format {
+            Format::Toml => toml::from_str(content).map_err(invalid_data),
+            Format::Json => serde_json::from_str(content).map_err(invalid_data),
+            Format::Yaml => serde_yaml::from_str(content).map_err(invalid_data),
+        }
+    }
+
+    /// Builds the `CliConfig`, failing if a command names an action that is
+    /// not registered or has neither an action nor a UI element.
+    pub fn into_config<'a>(self, registry: &ActionRegistry<'a>) -> io::Result<CliConfig<'a>> {
+        let mut cli = CliConfig::new(self.name, self.version, self.about);
+        if let Some(prefix) = self.env_prefix {
+            cli.set_env_prefix(prefix);
+        }
+
+        for command in self.commands {
+            let (name, description) = (command.name, command.description);
+
+            let config = match (command.action, command.ui) {
+                (Some(action), None) => {
+                    let command_type = registry.get(&action).cloned().ok_or_else(|| {
+                        invalid_spec(format!(
+                            "Command '{}' uses unknown action '{}'",
+                            name, action
+                        ))
+                    })?;
+                    cli.push_command(CommandConfig::new(name, description, command_type))
+                }
+                (None, Some(ui)) => add_ui(&mut cli, name, description, ui),
+                _ => {
+                    return Err(invalid_spec(format!(
+                        "Command '{}' needs exactly one of `action` or `ui`",
+                        name
+                    )))
+                }
+            };
+
+            config.examples.extend(command.examples);
+            config.long_description = command.long_description;
+            config.aliases.extend(command.aliases);
+            config.see_also.extend(command.see_also);
+            config.group = command.group;
+            config.hidden = command.hidden;
+            config.deprecated = command.deprecated;
+            for arg in command.args {
+                config.add_arg(ArgConfig {
+                    name: arg.name.into(),
+                    help: arg.help,
+                    required: arg.required,
+                    long: arg.long.map(Into::into),
+                    short: arg.short,
+                    env: arg.env.map(Into::into),
+                    show_env_value: arg.show_env_value,
+                    default_value: arg.default.map(Into::into),
+                    help_heading: arg.help_heading,
+                    hidden: arg.hidden,
+                    flag: arg.flag,
+                    conflicts_with: arg.conflicts_with,
+                    requires: arg.requires,
+                    required_unless_present: arg.required_unless_present,
+                    ..Default::default()
+                });
+            }
+            for group in command.groups {
+                config.add_group(ArgGroupConfig {
+                    name: group.name,
+                    args: group.args,
+                    required: group.required,
+                    multiple: group.multiple,
+                });
+            }
+        }
+
+        Ok(cli)
+    }
+}
+
+impl<'a> CliConfig<'a> {
+    pub fn from_spec_file(path: &Path, registry: &ActionRegistry<'a>) -> io::Result<Self> {
+        CliSpec::from_file(path)?.into_config(registry)
+    }
+}
+
+fn add_ui<'c, 'a>(
+    cli: &'c mut CliConfig<'a>,
+    name: String,
+    description: String,
+    ui: UiSpec,
+) -> &'c mut CommandConfig<'a> {
+    match ui {
+        UiSpec::Input {
+            placeholder,
+            initial_text,
+            label,
+        } => cli.add_input(name, description, placeholder, initial_text, label),
+        UiSpec::TextArea {
+            label,
+            visible_lines,
+            initial_text,
+            max_length,
+            max_lines,
+        } => cli.add_text_area_with_options(
+            name,
+            description,
+            visible_lines,
+            Arc::new(move |_| label.clone()),
+            TextAreaOptions {
+                initial_text,
+                max_length,
+                max_lines,
+                ..Default::default()
+            },
+        ),
+        UiSpec::MenuList {
+            title,
+            subtitle,
+            items,
+        } => cli.add_menu_list(name, description, title, subtitle, items),
+        UiSpec::ItemList { title, items } => {
+            let items = items
+                .into_iter()
+                .map(|item| Item {
+                    title: item.title,
+                    subtitle: item.subtitle,
+                })
+                .collect();
+            cli.add_item_list(name, description, items, title)
+        }
+        UiSpec::Table { headers, rows } => cli.add_table_with(
+            name,
+            description,
+            Arc::new(move |_| crate::config::TableData {
+                headers: headers.clone(),
+                rows: rows.clone(),
+            }),
+        ),
+        UiSpec::Loader { text, style } => cli.add_loader(name, description, text, style),
+        UiSpec::Timer { secs } => cli.add_timer(name, description, secs, 0),
+        UiSpec::Stopwatch => cli.add_stopwatch(name, description),
+        UiSpec::Viewport { file } => cli.add_viewport(name, description, file),
+    }
+}
+
+fn invalid_spec(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn invalid_data<E: std::error::Error + Send + Sync + 'static>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEC: &str = r#"
+name = "spec_app"
+version = "2.0"
+about = "Defined in TOML"
+
+[[commands]]
+name = "greet"
+description = "Greet someone"
+action = "greet"
+args = [{ name = "name", help = "Name to greet", long = "name", default = "world" }]
+
+[[commands]]
+name = "color"
+description = "Pick a color"
+ui = { type = "menu_list", title = "Colors", items = ["red", "blue"] }
+"#;
+
+    #[test]
+    fn test_spec_into_config() {
+        let mut registry = ActionRegistry::new();
+        registry.register_standard("greet", Arc::new(|_| {}));
+
+        let cli = CliSpec::parse(SPEC, Format::Toml)
+            .unwrap()
+            .into_config(&registry)
+            .unwrap();
+
+        assert_eq!(cli.app_name, "spec_app");
+        assert_eq!(cli.commands().len(), 2);
+        assert_eq!(
+            cli.commands()[0].args[0].default_value.as_deref(),
+            Some("world")
+        );
+        assert!(matches!(
+            cli.commands()[1].command_type,
+            CommandType::UIWithReturn(_)
+        ));
+    }
+
+    #[test]
+    fn test_spec_unknown_action() {
+        let result = CliSpec::parse(SPEC, Format::Toml)
+            .unwrap()
+            .into_config(&ActionRegistry::new());
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_spec_misspelled_key() {
+        // The text replaced in SPEC, what replaces it, and the misspelled key
+        let typos = [
+            ("name = \"greet\"", "nmae = \"greet\"", "nmae"),
+            (
+                "long = \"name\"",
+                "long = \"name\", requird = true",
+                "requird",
+            ),
+            ("title = \"Colors\"", "titel = \"Colors\"", "titel"),
+        ];
+        for (text, typo, key) in typos {
+            let spec = SPEC.replacen(text, typo, 1);
+            let err = CliSpec::parse(&spec, Format::Toml).unwrap_err();
+
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            assert!(err.to_string().contains(key), "{}", err);
+        }
+    }
+}
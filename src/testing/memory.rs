@@ -0,0 +1,403 @@
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use ratatui::backend::TestBackend;
+use ratatui::Terminal;
+use rustubble::command::CommandInfo;
+use rustubble::help::HelpComponent;
+use rustubble::input::TextInput;
+use rustubble::list::{Item, ItemList};
+use rustubble::menu_list::Menu;
+use rustubble::text_area::TextArea;
+
+use super::screen::{buffer_text, capture_stdout, Screen};
+use super::Keys;
+use crate::signals::cancelled;
+use crate::ui::{self, Backend, EventSource, Frame, Prompt, Shown, View};
+
+// The padding of inputs, as on a terminal
+const PADDING: usize = 2;
+// The sizes rustubble draws menus at, and that lists get here
+const MENU_WIDTH: u16 = 40;
+const MENU_HEIGHT: u16 = 50;
+const LIST_WIDTH: u16 = 80;
+const LIST_HEIGHT: u16 = 24;
+
+/// Answers prompts from scripted keys and keeps what was shown and printed,
+/// for tests. Clones share their keys and records.
+///
+/// The keys drive the rustubble prompts themselves, as on a terminal, and
+/// every state of a prompt or view is kept as the plain text it draws: menus
+/// and lists draw into a buffer, inputs and text areas on a captured stdout,
+/// so they can only be scripted on Unix. Views that tick,
+/// like timers, keep ticking once the keys run out, as on a terminal nobody
+/// types on.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryBackend {
+    keys: Arc<Mutex<VecDeque<KeyEvent>>>,
+    frames: Arc<Mutex<Vec<String>>>,
+    printed: Arc<Mutex<Vec<String>>>,
+}
+
+impl MemoryBackend {
+    /// A backend without keys, whose prompts fail right away.
+    pub fn new() -> Self {
+        MemoryBackend::default()
+    }
+
+    pub fn with_keys(keys: Keys) -> Self {
+        MemoryBackend {
+            keys: Arc::new(Mutex::new(keys.events.into())),
+            ..MemoryBackend::default()
+        }
+    }
+
+    /// Every frame shown so far, in order.
+    pub fn frames(&self) -> Vec<String> {
+        self.frames.lock().unwrap().clone()
+    }
+
+    /// Every line printed so far, in order.
+    pub fn printed(&self) -> Vec<String> {
+        self.printed.lock().unwrap().clone()
+    }
+
+    // Fails once the script runs out rather than waiting forever
+    fn next_key(&self) -> io::Result<KeyEvent> {
+        self.keys
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "ran out of scripted keys"))
+    }
+
+    fn record(&self, frame: String) {
+        self.frames.lock().unwrap().push(frame);
+    }
+
+    fn input(
+        &self,
+        label: &str,
+        placeholder: &str,
+        initial_text: &str,
+        prefix: &str,
+        helper: &str,
+    ) -> io::Result<Option<String>> {
+        let mut input = TextInput::new(
+            Some(placeholder),
+            PADDING,
+            initial_text,
+            label,
+            Some(helper),
+            prefix,
+        );
+        let mut screen = Screen::default();
+        loop {
+            screen.play(&capture_stdout(|| input.render(0, 0))?);
+            self.record(screen.text());
+
+            // Only the keys that change the input draw it again
+            loop {
+                let key = self.next_key()?;
+                match key.code {
+                    _ if is_ctrl_c(&key) => return Ok(None),
+                    KeyCode::Char(c) => input.insert_char(c),
+                    KeyCode::Enter => {
+                        // The placeholder shows when the input is empty, and
+                        // neither it nor an empty input can be submitted
+                        let value = screen.text_at(2, PADDING + prefix.chars().count() + 1);
+                        if !value.is_empty() && value != placeholder {
+                            return Ok(Some(value));
+                        }
+                        continue;
+                    }
+                    KeyCode::Backspace => input.delete_char(),
+                    KeyCode::Left => input.move_cursor_left(),
+                    KeyCode::Right => input.move_cursor_right(),
+                    KeyCode::Esc => return Ok(None),
+                    _ => continue,
+                }
+                break;
+            }
+        }
+    }
+
+    fn text_area(
+        &self,
+        label: &str,
+        initial_text: &str,
+        visible_lines: usize,
+        helper: &str,
+    ) -> io::Result<Option<String>> {
+        let mut text_area = ui::prefilled_text_area(label, helper, visible_lines, initial_text);
+        let mut screen = Screen::default();
+        let mut typed = Vec::new();
+        loop {
+            screen.play(&capture_stdout(|| text_area.render(0, 0))?);
+            self.record(screen.text());
+
+            let key = self.next_key()?;
+            match key.code {
+                _ if is_ctrl_c(&key) => return Ok(None),
+                KeyCode::Tab => return full_text(initial_text, &typed).map(Some),
+                KeyCode::Esc => return Ok(None),
+                _ => {
+                    edit(&mut text_area, &key);
+                    typed.push(key);
+                }
+            }
+        }
+    }
+
+    fn menu(&self, title: &str, subtitle: &str, options: &[String]) -> io::Result<Option<String>> {
+        let mut menu = Menu::new(title.to_string(), subtitle.to_string(), options.to_vec());
+        let width = options
+            .iter()
+            .map(|option| option.chars().count() as u16 + 4)
+            .fold(MENU_WIDTH, u16::max);
+        let mut terminal = Terminal::new(TestBackend::new(width, MENU_HEIGHT))?;
+        let commands = vec![
+            CommandInfo::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            CommandInfo::new(KeyCode::Char('q'), KeyModifiers::NONE),
+            CommandInfo::new(KeyCode::Enter, KeyModifiers::NONE),
+            CommandInfo::new(KeyCode::Down, KeyModifiers::NONE),
+            CommandInfo::new(KeyCode::Up, KeyModifiers::NONE),
+        ];
+        let mut help = HelpComponent::new(commands, Vec::new());
+        loop {
+            let area = terminal.size()?;
+            menu.render(&mut terminal, area, &mut help);
+            let frame = buffer_text(terminal.backend().buffer());
+            self.record(frame.clone());
+
+            let key = self.next_key()?;
+            match key.code {
+                _ if is_ctrl_c(&key) => return Ok(None),
+                KeyCode::Char('q') => return Ok(None),
+                KeyCode::Char('j') | KeyCode::Down => menu.down(),
+                KeyCode::Char('k') | KeyCode::Up => menu.up(),
+                KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    menu.toggle_selection()
+                }
+                KeyCode::Enter => return Ok(highlighted_option(&frame, options)),
+                _ => {}
+            }
+        }
+    }
+
+    fn list(&self, title: &str, items: &[Item]) -> io::Result<Option<String>> {
+        let mut terminal = Terminal::new(TestBackend::new(LIST_WIDTH, LIST_HEIGHT))?;
+        let commands = vec![
+            CommandInfo::new(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            CommandInfo::new(KeyCode::Char('q'), KeyModifiers::NONE),
+            CommandInfo::new(KeyCode::Char('/'), KeyModifiers::NONE),
+            CommandInfo::new(KeyCode::Enter, KeyModifiers::NONE),
+            CommandInfo::new(KeyCode::Down, KeyModifiers::NONE),
+            CommandInfo::new(KeyCode::Up, KeyModifiers::NONE),
+        ];
+        let filter_commands = vec![
+            CommandInfo::new(KeyCode::Esc, KeyModifiers::NONE),
+            CommandInfo::new(KeyCode::Enter, KeyModifiers::NONE),
+        ];
+        let mut help = HelpComponent::new(commands, filter_commands);
+
+        let mut filter = Filter {
+            title,
+            items,
+            text: String::new(),
+            showing: false,
+        };
+        let mut list = filter.list(None);
+        loop {
+            if filter.showing {
+                help.activate_filter_mode();
+            } else {
+                help.deactivate_filter_mode();
+            }
+            let area = terminal.size()?;
+            list.render(&mut terminal, area, &mut help);
+            self.record(buffer_text(terminal.backend().buffer()));
+
+            let key = self.next_key()?;
+            let selected = list.get_selected_item().cloned();
+            match key.code {
+                _ if is_ctrl_c(&key) => return Ok(None),
+                KeyCode::Char('/') => {
+                    filter.showing = !filter.showing;
+                    list = filter.list(selected.as_ref());
+                }
+                KeyCode::Esc => {
+                    filter.showing = false;
+                    list = filter.list(selected.as_ref());
+                }
+                KeyCode::Char('q') => return Ok(None),
+                KeyCode::Down if selected.is_some() => list.next(),
+                KeyCode::Up if selected.is_some() => list.previous(),
+                KeyCode::Enter => {
+                    if let Some(item) = selected {
+                        return Ok(Some(item.title));
+                    }
+                }
+                KeyCode::Char(c) if filter.showing => {
+                    filter.text.push(c);
+                    list = filter.list(None);
+                }
+                KeyCode::Backspace if filter.showing => {
+                    filter.text.pop();
+                    list = filter.list(None);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn prompt(&mut self, prompt: &Prompt) -> io::Result<Option<String>> {
+        match *prompt {
+            Prompt::Input {
+                label,
+                placeholder,
+                initial_text,
+                prefix,
+                helper,
+            } => self.input(label, placeholder, initial_text, prefix, helper),
+            Prompt::TextArea {
+                label,
+                initial_text,
+                visible_lines,
+                helper,
+            } => self.text_area(label, initial_text, visible_lines, helper),
+            Prompt::Menu {
+                title,
+                subtitle,
+                options,
+            } => self.menu(title, subtitle, options),
+            Prompt::List { title, items } => self.list(title, items),
+        }
+    }
+
+    fn show(&mut self, view: &View) -> io::Result<()> {
+        let mut widget = match view.shown() {
+            Shown::Live(frame) => {
+                self.frames.lock().unwrap().push(frame.to_string());
+                return Ok(());
+            }
+            Shown::Widget(widget) => widget,
+        };
+        let mut record = |frame: &Frame| {
+            self.frames.lock().unwrap().push(frame.to_string());
+            Ok(())
+        };
+        let shown = ui::run(&mut *widget, &mut Script(&self.keys), &mut record)?;
+        shown.ok_or_else(cancelled)
+    }
+
+    fn print(&mut self, line: &str) -> io::Result<()> {
+        self.printed.lock().unwrap().push(line.to_string());
+        Ok(())
+    }
+}
+
+// The keys of a backend as events for views
+struct Script<'a>(&'a Mutex<VecDeque<KeyEvent>>);
+
+impl EventSource for Script<'_> {
+    fn read_event(&mut self) -> io::Result<Event> {
+        self.0.lock().unwrap().read_event()
+    }
+
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        self.0.lock().unwrap().poll_event(timeout)
+    }
+}
+
+fn is_ctrl_c(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+// What `handle_text_area` does with the keys that edit
+fn edit(text_area: &mut TextArea, key: &KeyEvent) {
+    match key.code {
+        KeyCode::Char(c) => text_area.insert_char(c),
+        KeyCode::Enter => text_area.insert_new_line(),
+        KeyCode::Backspace => text_area.delete_char(),
+        KeyCode::Left => text_area.move_cursor_left(),
+        KeyCode::Right => text_area.move_cursor_right(),
+        KeyCode::Down => text_area.move_cursor_down(),
+        KeyCode::Up => text_area.move_cursor_up(),
+        _ => {}
+    }
+}
+
+// A text area keeps its text to itself and shows only some of its lines, so
+// the keys are typed again into one tall enough to show every line, and the
+// text is read off its lines
+fn full_text(initial_text: &str, typed: &[KeyEvent]) -> io::Result<String> {
+    let newlines = typed.iter().filter(|key| key.code == KeyCode::Enter);
+    let lines = initial_text.split('\n').count() + newlines.count();
+    let mut text_area = ui::prefilled_text_area("", "", lines, initial_text);
+    typed.iter().for_each(|key| edit(&mut text_area, key));
+    // The cursor stops on the last line, which shows where the text ends
+    (0..lines).for_each(|_| text_area.move_cursor_down());
+
+    let mut screen = Screen::default();
+    screen.play(&capture_stdout(|| text_area.render(0, 0))?);
+    let (_, last) = screen.cursor();
+    let text: Vec<String> = (2..=last)
+        .map(|row| {
+            // Past the `|  1 ` line number
+            let line = screen.text_at(row, 1);
+            let line = line.trim_start();
+            line.split_once(' ')
+                .map_or("", |(_, text)| text)
+                .to_string()
+        })
+        .collect();
+    Ok(text.join("\n"))
+}
+
+// A menu only shows which option is highlighted, with `> ` before it
+fn highlighted_option(frame: &str, options: &[String]) -> Option<String> {
+    let line = frame.lines().find_map(|line| line.strip_prefix("> "))?;
+    // Past the `✓ ` of toggled options
+    let shown: String = line.chars().skip(2).collect();
+    options.iter().find(|option| **option == shown).cloned()
+}
+
+// rustubble keeps the filter of a list to itself, so a list is made of the
+// items matching the filter, titled as rustubble titles a filtered list
+struct Filter<'a> {
+    title: &'a str,
+    items: &'a [Item],
+    text: String,
+    showing: bool,
+}
+
+impl Filter<'_> {
+    fn list(&self, selected: Option<&Item>) -> ItemList {
+        let text = self.text.to_lowercase();
+        let matching: Vec<Item> = self
+            .items
+            .iter()
+            .filter(|item| item.title.to_lowercase().contains(&text))
+            .cloned()
+            .collect();
+        let title = if self.showing {
+            format!("Filter: {}", self.text)
+        } else {
+            self.title.to_string()
+        };
+        let index = selected.and_then(|selected| {
+            matching
+                .iter()
+                .position(|item| item.title == selected.title && item.subtitle == selected.subtitle)
+        });
+        let mut list = ItemList::new(title, matching);
+        (0..index.unwrap_or(0)).for_each(|_| list.next());
+        list
+    }
+}
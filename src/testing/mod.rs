@@ -0,0 +1,225 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use std::sync::{Arc, Mutex};
+
+use crate::ui;
+
+mod memory;
+mod screen;
+
+pub use memory::MemoryBackend;
+
+/// A script of key presses for `with_keys` and `MemoryBackend`.
+#[derive(Clone, Debug, Default)]
+pub struct Keys {
+    events: Vec<KeyEvent>,
+}
+
+impl Keys {
+    pub fn new() -> Self {
+        Keys::default()
+    }
+
+    pub fn key(mut self, code: KeyCode, modifiers: KeyModifiers) -> Self {
+        self.events.push(KeyEvent::new(code, modifiers));
+        self
+    }
+
+    pub fn press(self, code: KeyCode) -> Self {
+        self.key(code, KeyModifiers::NONE)
+    }
+
+    pub fn ctrl(self, c: char) -> Self {
+        self.key(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    /// Presses the key of every char in `text`.
+    pub fn type_text(self, text: &str) -> Self {
+        text.chars()
+            .fold(self, |keys, c| keys.press(KeyCode::Char(c)))
+    }
+
+    pub fn enter(self) -> Self {
+        self.press(KeyCode::Enter)
+    }
+
+    pub fn esc(self) -> Self {
+        self.press(KeyCode::Esc)
+    }
+
+    pub fn tab(self) -> Self {
+        self.press(KeyCode::Tab)
+    }
+
+    pub fn up(self) -> Self {
+        self.press(KeyCode::Up)
+    }
+
+    pub fn down(self) -> Self {
+        self.press(KeyCode::Down)
+    }
+
+    pub fn backspace(self) -> Self {
+        self.press(KeyCode::Backspace)
+    }
+}
+
+/// What `with_keys` saw: the value the closure returned, every frame shown
+/// as plain text and every status line printed, in order.
+#[derive(Debug)]
+pub struct Run<T> {
+    pub output: T,
+    pub frames: Vec<String>,
+    pub printed: Vec<String>,
+}
+
+impl<T> Run<T> {
+    /// The last frame shown.
+    pub fn last_frame(&self) -> String {
+        self.frames.last().cloned().unwrap_or_default()
+    }
+}
+
+/// Runs `f` with every prompt it asks answered from `keys` by a
+/// `MemoryBackend`, instead of the terminal.
+///
+/// A prompt that runs out of keys fails with `UnexpectedEof` rather than
+/// waiting. Sessions are per thread, so tests can run in parallel.
+pub fn with_keys<T>(keys: Keys, f: impl FnOnce() -> T) -> Run<T> {
+    let memory = MemoryBackend::with_keys(keys);
+    let previous_backend = ui::replace_local_backend(Some(Arc::new(Mutex::new(memory.clone()))));
+    let output = f();
+    ui::replace_local_backend(previous_backend);
+    Run {
+        output,
+        frames: memory.frames(),
+        printed: memory.printed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runtime::block_on;
+    use crate::signals::is_cancelled;
+    use crate::wrappers::{
+        input_form, item_list, menu_list, progress_while, spin_while, table, text_area_with,
+        TextAreaOptions,
+    };
+    use rustubble::list::Item;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    #[test]
+    fn test_input_form_returns_typed_text() {
+        let run = with_keys(Keys::new().type_text("abc").enter(), || {
+            input_form("Your name", "", "Name")
+        });
+
+        assert_eq!(run.frames.len(), 4);
+        assert!(run.frames[0].contains("  > Your name"));
+        assert_eq!(run.last_frame(), "  Name\n\n  > abc\n\n\n  Ctrl+C to exit");
+        assert_eq!(run.output.unwrap(), Some("abc".to_string()));
+    }
+
+    #[test]
+    fn test_menu_list_picks_highlighted_option() {
+        let options = vec!["red".to_string(), "green".to_string(), "blue".to_string()];
+        let run = with_keys(Keys::new().down().down().up().enter(), || {
+            menu_list(options, "Color".to_string(), String::new())
+        });
+
+        // The highlight symbol, then the room for the toggle mark
+        assert!(run.last_frame().contains("\n>   green\n"));
+        assert!(run.printed.is_empty());
+        assert_eq!(run.output.unwrap(), Some("green".to_string()));
+    }
+
+    #[test]
+    fn test_text_area_keeps_lines_out_of_view() {
+        let keys = Keys::new().enter().type_text("bis").tab();
+        let options = TextAreaOptions {
+            initial_text: "café au lait\nthé".to_string(),
+            ..TextAreaOptions::default()
+        };
+        let run = with_keys(keys, || text_area_with("Order", 1, &options));
+
+        assert!(run.last_frame().starts_with("Order\n\n|  3 bis"));
+        assert_eq!(
+            run.output.unwrap(),
+            Some("café au lait\nthé\nbis".to_string())
+        );
+    }
+
+    #[test]
+    fn test_item_list_filters_items() {
+        let item = |title: &str| Item {
+            title: title.to_string(),
+            subtitle: format!("About {}", title),
+        };
+        let run = with_keys(Keys::new().type_text("/gin").esc().enter(), || {
+            item_list(vec![item("Pocky"), item("Ginger")], "Snacks".to_string())
+        });
+
+        assert!(run.frames[4].starts_with("Filter: gin\n"));
+        assert!(!run.last_frame().contains("Pocky"));
+        assert_eq!(run.output.unwrap(), Some("Ginger".to_string()));
+    }
+
+    #[test]
+    fn test_cancel_and_running_out_of_keys() {
+        let run = with_keys(Keys::new().type_text("ab").ctrl('c'), || {
+            input_form("", "", "Name")
+        });
+        assert!(is_cancelled(&run.output.unwrap_err()));
+
+        let run = with_keys(Keys::new().type_text("ab"), || input_form("", "", "Name"));
+        assert_eq!(
+            run.output.unwrap_err().kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn test_views_are_kept_as_frames() {
+        let row = |cells: [&str; 2]| cells.map(str::to_string).to_vec();
+        let run = with_keys(Keys::new().down().press(KeyCode::Char('q')), || {
+            table(row(["Name", "Age"]), vec![row(["Ann", "31"])])
+        });
+
+        assert!(run.output.is_ok());
+        assert_eq!(run.frames.len(), 3);
+        // The borders are ASCII on terminals without UTF-8
+        assert!(run.last_frame().contains(" Name  Age "));
+        assert!(run.last_frame().contains(" Ann   31  "));
+        assert!(run.last_frame().ends_with("q quit"));
+
+        // Views ask for keys too
+        let run = with_keys(Keys::new(), || table(row(["Name", "Age"]), Vec::new()));
+        assert_eq!(
+            run.output.unwrap_err().kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn test_live_views_go_through_the_backend() {
+        let run = with_keys(Keys::new(), || {
+            block_on(spin_while("Loading", async {
+                sleep(Duration::from_millis(100));
+                7
+            }))
+        });
+        assert_eq!(run.output, 7);
+        assert!(run.frames.iter().all(|frame| frame.ends_with(" Loading")));
+        assert!(!run.frames.is_empty());
+
+        let run = with_keys(Keys::new(), || {
+            block_on(progress_while("Copying", |progress| async move {
+                progress.set(0.5);
+                sleep(Duration::from_millis(100));
+            }))
+        });
+        assert!(run.frames.iter().any(|frame| frame.ends_with(" 50%")));
+    }
+}
@@ -0,0 +1,200 @@
+use std::io;
+
+use ratatui::buffer::Buffer;
+
+/// What a terminal would show after the escape sequences the rustubble
+/// prompts print: moves, line and screen clears and text. Colors are dropped.
+#[derive(Debug, Default)]
+pub(super) struct Screen {
+    // Cells nothing was printed in, or that were cleared, are `None`
+    rows: Vec<Vec<Option<char>>>,
+    row: usize,
+    column: usize,
+}
+
+impl Screen {
+    pub fn play(&mut self, output: &str) {
+        let mut chars = output.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\x1b' if chars.peek() == Some(&'[') => {
+                    chars.next();
+                    let mut params = String::new();
+                    // Parameters and intermediates end at the final byte
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            self.control(&params, c);
+                            break;
+                        }
+                        params.push(c);
+                    }
+                }
+                '\x1b' => {
+                    chars.next();
+                }
+                '\r' => self.column = 0,
+                '\n' => {
+                    self.row += 1;
+                    self.column = 0;
+                }
+                c if c.is_control() => {}
+                c => {
+                    let column = self.column;
+                    let row = self.row_mut(self.row);
+                    if row.len() <= column {
+                        row.resize(column + 1, None);
+                    }
+                    row[column] = Some(c);
+                    self.column += 1;
+                }
+            }
+        }
+    }
+
+    fn control(&mut self, params: &str, command: char) {
+        let mut numbers = params.split(';').map(|n| n.parse::<usize>().ok());
+        let mut number = || numbers.next().flatten();
+        match command {
+            'H' | 'f' => {
+                self.row = number().unwrap_or(1).saturating_sub(1);
+                self.column = number().unwrap_or(1).saturating_sub(1);
+            }
+            'G' => self.column = number().unwrap_or(1).saturating_sub(1),
+            'K' => {
+                let column = self.column;
+                let row = self.row_mut(self.row);
+                match number().unwrap_or(0) {
+                    0 => row.truncate(column),
+                    1 => row
+                        .iter_mut()
+                        .take(column + 1)
+                        .for_each(|cell| *cell = None),
+                    _ => row.clear(),
+                }
+            }
+            'J' => match number().unwrap_or(0) {
+                0 => {
+                    let (row, column) = (self.row, self.column);
+                    self.rows.truncate(row + 1);
+                    self.row_mut(row).truncate(column);
+                }
+                2 | 3 => self.rows.clear(),
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+
+    fn row_mut(&mut self, row: usize) -> &mut Vec<Option<char>> {
+        if self.rows.len() <= row {
+            self.rows.resize(row + 1, Vec::new());
+        }
+        &mut self.rows[row]
+    }
+
+    /// Where the cursor is, as (column, row).
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.column, self.row)
+    }
+
+    /// What was printed on `row` from `column` on, up to the first blank
+    /// cell. Printed spaces count as text.
+    pub fn text_at(&self, row: usize, column: usize) -> String {
+        let cells = self.rows.get(row).map(Vec::as_slice).unwrap_or_default();
+        cells.iter().skip(column).map_while(|cell| *cell).collect()
+    }
+
+    /// The whole screen as plain text, without trailing blanks.
+    pub fn text(&self) -> String {
+        let lines = self.rows.iter().map(|row| {
+            let line: String = row.iter().map(|cell| cell.unwrap_or(' ')).collect();
+            line.trim_end().to_string()
+        });
+        trim_lines(lines)
+    }
+}
+
+/// A ratatui buffer as plain text, without trailing blanks.
+pub(super) fn buffer_text(buffer: &Buffer) -> String {
+    let area = buffer.area;
+    let lines = (area.top()..area.bottom()).map(|y| {
+        let line: String = (area.left()..area.right())
+            .map(|x| buffer.get(x, y).symbol())
+            .collect();
+        line.trim_end().to_string()
+    });
+    trim_lines(lines)
+}
+
+fn trim_lines(lines: impl Iterator<Item = String>) -> String {
+    let lines: Vec<String> = lines.collect();
+    let end = lines
+        .iter()
+        .rposition(|line| !line.is_empty())
+        .map_or(0, |i| i + 1);
+    lines[..end].join("\n")
+}
+
+/// Everything `draw` writes to stdout, kept off the terminal.
+///
+/// The rustubble inputs and text areas only draw on stdout, so stdout is
+/// pointed at a temporary file while they do. Other threads wait for stdout
+/// until then.
+#[cfg(unix)]
+pub(super) fn capture_stdout(draw: impl FnOnce()) -> io::Result<String> {
+    use std::fs::{self, OpenOptions};
+    use std::io::{Read, Seek, SeekFrom, Write};
+    use std::os::fd::AsRawFd;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static CAPTURES: AtomicUsize = AtomicUsize::new(0);
+
+    let stdout = io::stdout();
+    let mut lock = stdout.lock();
+    lock.flush()?;
+
+    let path = std::env::temp_dir().join(format!(
+        "bubblers-screen-{}-{}",
+        std::process::id(),
+        CAPTURES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    fs::remove_file(&path)?;
+
+    let redirect = crate::ui::Redirect::stdout_to(file.as_raw_fd())?;
+    draw();
+    lock.flush()?;
+    drop(redirect);
+
+    let mut output = String::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_string(&mut output)?;
+    Ok(output)
+}
+
+#[cfg(not(unix))]
+pub(super) fn capture_stdout(_draw: impl FnOnce()) -> io::Result<String> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "inputs and text areas can only be scripted on Unix",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plays_moves_and_clears() {
+        let mut screen = Screen::default();
+        screen.play("\x1b[1;3HName\x1b[3;1H\x1b[38;5;15m  > abc\x1b[39m");
+        screen.play("\x1b[3;1H\x1b[2K  > ab ");
+        assert_eq!(screen.text(), "  Name\n\n  > ab");
+        assert_eq!(screen.text_at(2, 4), "ab ");
+        assert_eq!(screen.cursor(), (7, 2));
+    }
+}
@@ -0,0 +1,574 @@
+use std::cell::RefCell;
+use std::io::{self, IsTerminal, Write};
+use std::sync::{Arc, Mutex};
+
+use crossterm::cursor::{self, MoveTo, MoveToColumn, MoveToPreviousLine};
+use crossterm::style::{
+    Attribute, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType};
+use crossterm::{execute, queue};
+use rustubble::input::{handle_input, TextInput};
+use rustubble::list::{handle_list, ItemList};
+use rustubble::menu_list::{handle_menu_list, Menu};
+use rustubble::text_area::{handle_text_area, TextArea};
+
+use super::{run, Capabilities, ColorSupport, Frame, Line, Prompt, Shown, TerminalEvents, View};
+use crate::signals::cancelled;
+
+/// Where prompts are asked and views shown.
+pub trait Backend: Send {
+    /// Asks until the user submits or cancels, returning `None` when
+    /// cancelled.
+    fn prompt(&mut self, prompt: &Prompt) -> io::Result<Option<String>>;
+
+    /// Shows until the view ends or the user closes it, failing with
+    /// `signals::cancelled()` on Ctrl+C. Live views are drawn and stay, see
+    /// `View`.
+    fn show(&mut self, view: &View) -> io::Result<()>;
+
+    /// Takes the live view off the screen.
+    fn clear(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Prints a status line, above the live view if any.
+    fn print(&mut self, line: &str) -> io::Result<()>;
+
+    /// Takes the live view off the screen while `write` runs, so that what it
+    /// writes to the terminal ends up above the view.
+    fn suspend(&mut self, write: &mut dyn FnMut() -> io::Result<()>) -> io::Result<()> {
+        write()
+    }
+}
+
+/// The stream a `CrosstermBackend` draws on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Stdout,
+    Stderr,
+}
+
+// Columns left blank on the left of every view
+const MARGIN: u16 = 2;
+
+/// Draws views on the terminal with crossterm, from the line of the cursor
+/// down, and asks with the rustubble prompts.
+///
+/// The prompts draw on stdout, so a backend on stderr points stdout at
+/// stderr while they run. Other platforms than Unix draw them on stdout
+/// either way. Colors and glyphs of views are downgraded to what the stream
+/// supports, see `Capabilities::detect`.
+pub struct CrosstermBackend {
+    target: Target,
+    out: Box<dyn Write + Send>,
+    // Whether `out` is a terminal, for detection
+    terminal: bool,
+    // Live views are left out of stdout and stderr when they aren't
+    // terminals, like in CI logs
+    live: bool,
+    capabilities: Option<Capabilities>,
+    // Rows from the first drawn line to the cursor
+    cursor_row: u16,
+    // The live view on screen, redrawn below printed lines
+    drawn: Option<Frame>,
+}
+
+impl CrosstermBackend {
+    pub fn stdout() -> Self {
+        let terminal = io::stdout().is_terminal();
+        CrosstermBackend {
+            terminal,
+            live: terminal,
+            ..CrosstermBackend::new(io::stdout())
+        }
+    }
+
+    /// Keeps stdout clean for the output of the command, as in `$(mycli pick)`.
+    pub fn stderr() -> Self {
+        let terminal = io::stderr().is_terminal();
+        CrosstermBackend {
+            target: Target::Stderr,
+            terminal,
+            live: terminal,
+            ..CrosstermBackend::new(io::stderr())
+        }
+    }
+
+    /// Draws views on any writer, live ones included, and asks on stdout.
+    pub fn new(out: impl Write + Send + 'static) -> Self {
+        CrosstermBackend {
+            target: Target::Stdout,
+            out: Box::new(out),
+            terminal: false,
+            live: true,
+            capabilities: None,
+            cursor_row: 0,
+            drawn: None,
+        }
+    }
+
+    /// Draws with `capabilities` instead of detecting them.
+    pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.capabilities = Some(capabilities);
+        self
+    }
+
+    pub fn target(&self) -> Target {
+        self.target
+    }
+
+    fn current_capabilities(&self) -> Capabilities {
+        self.capabilities
+            .unwrap_or_else(|| Capabilities::detect(self.terminal))
+    }
+
+    // Runs the prompts of `draw` with stdout on the target
+    fn on_target<T>(&self, draw: impl FnOnce(u16, u16) -> T) -> io::Result<T> {
+        let _redirect = match self.target {
+            Target::Stdout => None,
+            Target::Stderr => Some(Redirect::stdout_to_stderr()?),
+        };
+        on_screen(draw)
+    }
+
+    fn move_to_start(&mut self) -> io::Result<()> {
+        // Terminals read a move by 0 as a move by 1
+        if self.cursor_row > 0 {
+            queue!(self.out, MoveToPreviousLine(self.cursor_row))?;
+        } else {
+            queue!(self.out, MoveToColumn(0))?;
+        }
+        self.cursor_row = 0;
+        Ok(())
+    }
+
+    // Clears the live view, returning it to draw it again later
+    fn take_off_screen(&mut self) -> io::Result<Option<Frame>> {
+        let drawn = self.drawn.take();
+        if drawn.is_some() {
+            self.move_to_start()?;
+            queue!(self.out, Clear(ClearType::FromCursorDown))?;
+        }
+        Ok(drawn)
+    }
+
+    // Replaces what was drawn last with `frame`
+    fn draw(&mut self, frame: &Frame) -> io::Result<()> {
+        let capabilities = self.current_capabilities();
+        self.move_to_start()?;
+        queue!(self.out, Clear(ClearType::FromCursorDown), cursor::Hide)?;
+        // "\r\n" starts a new line in raw mode too
+        for (row, line) in frame.lines.iter().enumerate() {
+            if row > 0 {
+                queue!(self.out, Print("\r\n"))?;
+            }
+            queue!(self.out, MoveToColumn(MARGIN))?;
+            write_line(&mut self.out, line, capabilities)?;
+        }
+        self.cursor_row = frame.lines.len().saturating_sub(1) as u16;
+        self.drawn = Some(frame.clone());
+        self.out.flush()
+    }
+
+    // Runs a view until it ends, reading keys in raw mode
+    fn run_view(&mut self, view: &View) -> io::Result<()> {
+        let mut widget = match view.shown() {
+            Shown::Live(frame) if self.live => return self.draw(&frame),
+            Shown::Live(_) => return Ok(()),
+            Shown::Widget(widget) => widget,
+        };
+        self.out.flush()?;
+        enable_raw_mode()?;
+        let result = run(&mut *widget, &mut TerminalEvents, &mut |frame| {
+            self.draw(frame)
+        });
+        disable_raw_mode()?;
+        self.clear()?;
+        result?.ok_or_else(cancelled)
+    }
+}
+
+/// Writes the spans of a line with their styles, downgraded to the
+/// capabilities of the terminal.
+pub(crate) fn write_line(
+    out: &mut impl Write,
+    line: &Line,
+    capabilities: Capabilities,
+) -> io::Result<()> {
+    for span in &line.spans {
+        let fg = span.fg.and_then(|color| capabilities.color(color));
+        let bg = span.bg.map(|color| capabilities.color(color));
+        if let Some(color) = fg {
+            queue!(out, SetForegroundColor(color))?;
+        }
+        match bg {
+            Some(Some(color)) => queue!(out, SetBackgroundColor(color))?,
+            // Keeps highlights visible without colors
+            Some(None) => queue!(out, SetAttribute(Attribute::Reverse))?,
+            None => {}
+        }
+        // Monochrome text stays plain, like messages
+        let bold = span.bold && capabilities.colors != ColorSupport::None;
+        if bold {
+            queue!(out, SetAttribute(Attribute::Bold))?;
+        }
+        queue!(out, Print(capabilities.text(&span.text)))?;
+        if fg.is_some() || bg.is_some() || bold {
+            queue!(out, SetAttribute(Attribute::Reset), ResetColor)?;
+        }
+    }
+    Ok(())
+}
+
+// Column and row the rustubble widgets are drawn at
+const POSITION: (u16, u16) = (5, 5);
+
+// Hands the cleared screen to the widgets, which read keys in raw mode
+fn on_screen<T>(draw: impl FnOnce(u16, u16) -> T) -> io::Result<T> {
+    let mut stdout = io::stdout();
+    stdout.flush()?;
+    enable_raw_mode()?;
+    execute!(stdout, Clear(ClearType::All))?;
+
+    let (x, y) = POSITION;
+    let result = draw(x, y);
+
+    execute!(stdout, Clear(ClearType::All), MoveTo(0, 0))?;
+    disable_raw_mode()?;
+    Ok(result)
+}
+
+fn prompt_terminal(prompt: &Prompt, x: u16, y: u16) -> Option<String> {
+    match *prompt {
+        Prompt::Input {
+            label,
+            placeholder,
+            initial_text,
+            prefix,
+            helper,
+        } => {
+            let mut input = TextInput::new(
+                Some(placeholder),
+                2,
+                initial_text,
+                label,
+                Some(helper),
+                prefix,
+            );
+            handle_input(&mut input, x, y)
+        }
+        Prompt::TextArea {
+            label,
+            initial_text,
+            visible_lines,
+            helper,
+        } => {
+            let mut text_area = prefilled_text_area(label, helper, visible_lines, initial_text);
+            handle_text_area(&mut text_area, x, y)
+        }
+        Prompt::Menu {
+            title,
+            subtitle,
+            options,
+        } => {
+            let mut menu = Menu::new(title.to_string(), subtitle.to_string(), options.to_vec());
+            handle_menu_list(&mut menu, x, y)
+        }
+        Prompt::List { title, items } => {
+            let mut list = ItemList::new(title.to_string(), items.to_vec());
+            handle_list(&mut list, x, y)
+        }
+    }
+}
+
+// A text area holding `initial_text`, with the cursor at its end.
+// `insert_char` steps the cursor by one per char but uses it as a byte index,
+// so the cursor is moved over the other bytes of each char, or the next char
+// would land inside this one and panic.
+pub(crate) fn prefilled_text_area(
+    label: &str,
+    helper: &str,
+    visible_lines: usize,
+    initial_text: &str,
+) -> TextArea {
+    let mut text_area = TextArea::new(label, Some(helper), visible_lines);
+    for c in initial_text.chars() {
+        text_area.insert_char(c);
+        if c != '\n' {
+            (1..c.len_utf8()).for_each(|_| text_area.move_cursor_right());
+        }
+    }
+    text_area
+}
+
+impl Backend for CrosstermBackend {
+    fn prompt(&mut self, prompt: &Prompt) -> io::Result<Option<String>> {
+        self.clear()?;
+        self.on_target(|x, y| prompt_terminal(prompt, x, y))
+    }
+
+    fn show(&mut self, view: &View) -> io::Result<()> {
+        self.run_view(view)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        if self.drawn.take().is_some() {
+            self.move_to_start()?;
+            queue!(self.out, Clear(ClearType::FromCursorDown), cursor::Show)?;
+        }
+        self.out.flush()
+    }
+
+    fn print(&mut self, line: &str) -> io::Result<()> {
+        let drawn = self.take_off_screen()?;
+        let capabilities = self.current_capabilities();
+        queue!(self.out, Print(capabilities.text(line)), Print("\n"))?;
+        match drawn {
+            Some(frame) => self.draw(&frame),
+            None => self.out.flush(),
+        }
+    }
+
+    fn suspend(&mut self, write: &mut dyn FnMut() -> io::Result<()>) -> io::Result<()> {
+        let drawn = self.take_off_screen()?;
+        self.out.flush()?;
+        let result = write();
+        if let Some(frame) = drawn {
+            self.draw(&frame)?;
+        }
+        result
+    }
+}
+
+// Points stdout at stderr, or another descriptor, until dropped
+#[cfg(unix)]
+pub(crate) struct Redirect {
+    saved: libc::c_int,
+}
+
+#[cfg(unix)]
+impl Redirect {
+    fn stdout_to_stderr() -> io::Result<Self> {
+        Redirect::stdout_to(libc::STDERR_FILENO)
+    }
+
+    pub(crate) fn stdout_to(fd: std::os::fd::RawFd) -> io::Result<Self> {
+        io::stdout().flush()?;
+        // SAFETY: dup and dup2 only copy file descriptors of this process
+        let saved = unsafe { libc::dup(libc::STDOUT_FILENO) };
+        if saved < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if unsafe { libc::dup2(fd, libc::STDOUT_FILENO) } < 0 {
+            let err = io::Error::last_os_error();
+            unsafe { libc::close(saved) };
+            return Err(err);
+        }
+        Ok(Redirect { saved })
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Redirect {
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+        // SAFETY: `saved` is the descriptor dup'ed in `stdout_to`
+        unsafe {
+            libc::dup2(self.saved, libc::STDOUT_FILENO);
+            libc::close(self.saved);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+struct Redirect;
+
+#[cfg(not(unix))]
+impl Redirect {
+    fn stdout_to_stderr() -> io::Result<Self> {
+        Ok(Redirect)
+    }
+}
+
+/// A backend shared by the prompts and views of a run, including live views
+/// drawn from another thread.
+pub type SharedBackend = Arc<Mutex<dyn Backend>>;
+
+static BACKEND: Mutex<Option<SharedBackend>> = Mutex::new(None);
+
+thread_local! {
+    // Set by `testing::with_keys` and runs, wins over the process-wide backend
+    static LOCAL_BACKEND: RefCell<Option<SharedBackend>> = const { RefCell::new(None) };
+}
+
+/// Makes every prompt and view go through `backend`.
+pub fn set_backend(backend: impl Backend + 'static) {
+    set_shared_backend(Arc::new(Mutex::new(backend)));
+}
+
+pub fn set_shared_backend(backend: SharedBackend) {
+    *BACKEND.lock().unwrap() = Some(backend);
+}
+
+/// The backend prompts and views go through.
+///
+/// Unless one was set, they draw on stdout, or on stderr when stdout is
+/// redirected so that it only carries the output of the command.
+pub fn backend() -> SharedBackend {
+    if let Some(backend) = LOCAL_BACKEND.with(|local| local.borrow().clone()) {
+        return backend;
+    }
+    BACKEND
+        .lock()
+        .unwrap()
+        .get_or_insert_with(default_backend)
+        .clone()
+}
+
+fn default_backend() -> SharedBackend {
+    Arc::new(Mutex::new(default_crossterm_backend(
+        io::stdout().is_terminal(),
+    )))
+}
+
+fn default_crossterm_backend(stdout_is_terminal: bool) -> CrosstermBackend {
+    if stdout_is_terminal {
+        CrosstermBackend::stdout()
+    } else {
+        CrosstermBackend::stderr()
+    }
+}
+
+pub(crate) fn replace_local_backend(backend: Option<SharedBackend>) -> Option<SharedBackend> {
+    LOCAL_BACKEND.with(|local| local.replace(backend))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::{ColorChoice, Io};
+    use crossterm::style::Color;
+    use std::time::Duration;
+
+    #[test]
+    fn test_redirected_stdout_draws_on_stderr() {
+        assert_eq!(default_crossterm_backend(true).target(), Target::Stdout);
+        assert_eq!(default_crossterm_backend(false).target(), Target::Stderr);
+    }
+
+    #[test]
+    fn test_live_views_redraw_in_place() {
+        let (io, _, stderr) = Io::null().capture();
+        let mut backend = CrosstermBackend::new(io.stderr());
+        for frame in 0..2 {
+            backend
+                .show(&View::LiveSpinner {
+                    message: "Loading",
+                    style: "Line",
+                    color: Color::Cyan,
+                    frame,
+                })
+                .unwrap();
+        }
+        backend.print("Done").unwrap();
+        backend.clear().unwrap();
+
+        let output = String::from_utf8(stderr.lock().unwrap().clone()).unwrap();
+        assert_eq!(output.matches("Loading").count(), 3);
+        // Back to the start of the line before each of the 3 draws, the
+        // print and the clear
+        assert_eq!(output.matches("\x1b[1G\x1b[J").count(), 5);
+        assert!(output.contains("Done\n"));
+        assert!(output.ends_with("\x1b[J\x1b[?25h"));
+    }
+
+    #[test]
+    fn test_views_follow_color_support() {
+        let theme = crate::ui::theme();
+        let (headers, rows) = (vec!["Name".to_string()], vec![vec!["Ann".to_string()]]);
+        let views = [
+            View::Spinner {
+                message: "Loading",
+                style: &theme.spinner,
+                color: theme.accent,
+            },
+            View::Table {
+                headers: &headers,
+                rows: &rows,
+            },
+            View::Progress {
+                prefix: "Copying",
+                progress: 0.5,
+                length: 10,
+                start_color: theme.primary,
+                end_color: theme.accent,
+            },
+            View::Timer {
+                duration: Duration::from_secs(5),
+            },
+            View::Stopwatch,
+            View::Viewport {
+                header: "notes.txt",
+                content: "Some notes",
+            },
+            View::LiveSpinner {
+                message: "Loading",
+                style: &theme.spinner,
+                color: theme.accent,
+                frame: 3,
+            },
+            View::LiveProgress {
+                prefix: "Copying",
+                progress: 0.5,
+                length: 10,
+                start_color: theme.primary,
+                end_color: theme.accent,
+            },
+        ];
+        let render = |view: &View, vars: &[(&str, &str)]| {
+            let capabilities = Capabilities::from_env(ColorChoice::Auto, true, |name| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            });
+            let frame = match view.shown() {
+                Shown::Live(frame) => frame,
+                Shown::Widget(widget) => widget.render(),
+            };
+            let mut out = Vec::new();
+            for line in &frame.lines {
+                write_line(&mut out, line, capabilities).unwrap();
+            }
+            String::from_utf8(out).unwrap()
+        };
+
+        for view in &views {
+            let no_color = render(view, &[("COLORTERM", "truecolor"), ("NO_COLOR", "1")]);
+            // Only the selected row of a table keeps a style, reverse video
+            let plain = no_color.replace("\x1b[7m", "").replace("\x1b[0m", "");
+            assert!(!plain.contains('\x1b'), "{:?}", no_color);
+
+            // crossterm writes the 16 basic colors as the first 16 of 256
+            let basic = render(view, &[("TERM", "xterm")]);
+            assert!(!basic.contains("\x1b[38;2;"));
+            assert!(basic.split("\x1b[38;5;").skip(1).all(|rest| {
+                let index = rest.split('m').next().unwrap();
+                index.parse::<u8>().unwrap() < 16
+            }));
+        }
+        let table = render(&views[1], &[("NO_COLOR", "1")]);
+        assert!(table.contains("\x1b[7m Ann  \x1b[0m"));
+    }
+
+    #[test]
+    fn test_text_area_prefills_non_ascii_text() {
+        let mut text_area = prefilled_text_area("Order", "", 3, "café au lait\nthé");
+
+        // Each one indexed into the middle of "é" before
+        text_area.insert_char('!');
+        text_area.delete_char();
+        text_area.insert_new_line();
+        text_area.insert_char('x');
+    }
+}
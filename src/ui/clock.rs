@@ -0,0 +1,172 @@
+use std::time::{Duration, Instant};
+
+use super::{dismiss, theme, Frame, Span, Step, Theme, Widget};
+use crossterm::event::{KeyCode, KeyEvent};
+
+const TICK: Duration = Duration::from_millis(50);
+
+// Like "4.250s", "1:05.000m" or "1:00:05.000h"
+fn format_duration(duration: Duration) -> String {
+    let millis = duration.subsec_millis();
+    let secs = duration.as_secs();
+    match secs {
+        0 => format!("{:03}ms", millis),
+        1..=59 => format!("{}.{:03}s", secs, millis),
+        60..=3599 => format!("{}:{:02}.{:03}m", secs / 60, secs % 60, millis),
+        _ => format!(
+            "{}:{:02}:{:02}.{:03}h",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60,
+            millis
+        ),
+    }
+}
+
+/// Counts down and closes when the time is up, or on q or Esc.
+#[derive(Clone, Debug)]
+pub(crate) struct Timer {
+    duration: Duration,
+    started: Instant,
+    theme: Theme,
+}
+
+impl Timer {
+    pub fn new(duration: Duration) -> Self {
+        Timer {
+            duration,
+            started: Instant::now(),
+            theme: theme(),
+        }
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.duration.saturating_sub(self.started.elapsed())
+    }
+}
+
+impl Widget for Timer {
+    type Output = ();
+
+    fn handle_key(&mut self, key: KeyEvent) -> Step<()> {
+        dismiss(&key)
+    }
+
+    fn render(&self) -> Frame {
+        let mut frame = Frame::new();
+        frame
+            .line(Span::new(format!(
+                "Exiting in {}",
+                format_duration(self.remaining())
+            )))
+            .blank()
+            .line(Span::new("q quit").fg(self.theme.muted));
+        frame
+    }
+
+    fn tick_rate(&self) -> Option<Duration> {
+        Some(TICK)
+    }
+
+    fn tick(&mut self) -> Step<()> {
+        if self.remaining().is_zero() {
+            return Step::Submit(());
+        }
+        Step::Continue
+    }
+}
+
+/// Counts up until q or Esc. Space pauses and r restarts it.
+#[derive(Clone, Debug)]
+pub(crate) struct Stopwatch {
+    // Time counted before the last start
+    counted: Duration,
+    started: Option<Instant>,
+    theme: Theme,
+}
+
+impl Default for Stopwatch {
+    fn default() -> Self {
+        Stopwatch::new()
+    }
+}
+
+impl Stopwatch {
+    pub fn new() -> Self {
+        Stopwatch {
+            counted: Duration::ZERO,
+            started: Some(Instant::now()),
+            theme: theme(),
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.counted
+            + self
+                .started
+                .map_or(Duration::ZERO, |started| started.elapsed())
+    }
+
+    pub fn toggle(&mut self) {
+        match self.started.take() {
+            Some(started) => self.counted += started.elapsed(),
+            None => self.started = Some(Instant::now()),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.counted = Duration::ZERO;
+        self.started = Some(Instant::now());
+    }
+}
+
+impl Widget for Stopwatch {
+    type Output = ();
+
+    fn handle_key(&mut self, key: KeyEvent) -> Step<()> {
+        match key.code {
+            KeyCode::Char(' ') => self.toggle(),
+            KeyCode::Char('r') => self.reset(),
+            _ => return dismiss(&key),
+        }
+        Step::Continue
+    }
+
+    fn render(&self) -> Frame {
+        let mut frame = Frame::new();
+        frame
+            .line(Span::new(format!(
+                "Elapsed: {}",
+                format_duration(self.elapsed())
+            )))
+            .blank()
+            .line(Span::new("space pause • r reset • q quit").fg(self.theme.muted));
+        frame
+    }
+
+    fn tick_rate(&self) -> Option<Duration> {
+        Some(TICK)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(42)), "042ms");
+        assert_eq!(format_duration(Duration::from_millis(4250)), "4.250s");
+        assert_eq!(format_duration(Duration::from_secs(65)), "1:05.000m");
+        assert_eq!(format_duration(Duration::from_secs(3605)), "1:00:05.000h");
+    }
+
+    #[test]
+    fn test_paused_stopwatch_stands_still() {
+        let mut stopwatch = Stopwatch::new();
+        stopwatch.toggle();
+        let elapsed = stopwatch.elapsed();
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(stopwatch.elapsed(), elapsed);
+    }
+}
@@ -0,0 +1,305 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::env;
+use std::sync::Mutex;
+
+use crossterm::style::Color;
+
+use crate::context::ColorChoice;
+
+/// How many colors a terminal can show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorSupport {
+    None,
+    /// The 16 named colors
+    Basic,
+    Ansi256,
+    TrueColor,
+}
+
+/// What a terminal can render, used to downgrade frames before drawing them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Capabilities {
+    pub colors: ColorSupport,
+    pub unicode: bool,
+}
+
+impl Capabilities {
+    /// Everything, for terminals known to support it.
+    pub fn full() -> Self {
+        Capabilities {
+            colors: ColorSupport::TrueColor,
+            unicode: true,
+        }
+    }
+
+    /// Detects what a stream can render from the environment and `--color`.
+    pub fn detect(is_terminal: bool) -> Self {
+        Capabilities::from_env(color_choice(), is_terminal, |name| env::var(name).ok())
+    }
+
+    pub(super) fn from_env(
+        choice: ColorChoice,
+        is_terminal: bool,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        let set = |name: &str| var(name).is_some_and(|value| !value.is_empty());
+        let term = var("TERM").unwrap_or_default();
+        let forced = var("CLICOLOR_FORCE").is_some_and(|value| !value.is_empty() && value != "0");
+        // See https://no-color.org and https://bixense.com/clicolors
+        let enabled = match choice {
+            ColorChoice::Never => false,
+            ColorChoice::Always => true,
+            ColorChoice::Auto if set("NO_COLOR") => false,
+            ColorChoice::Auto if forced => true,
+            ColorChoice::Auto => {
+                is_terminal && term != "dumb" && var("CLICOLOR").as_deref() != Some("0")
+            }
+        };
+
+        let colorterm = var("COLORTERM").unwrap_or_default();
+        let colors = if !enabled {
+            ColorSupport::None
+        } else if colorterm == "truecolor" || colorterm == "24bit" || set("WT_SESSION") {
+            ColorSupport::TrueColor
+        } else if term.contains("256color") {
+            ColorSupport::Ansi256
+        } else {
+            ColorSupport::Basic
+        };
+
+        // The first of these that is set wins, as with setlocale
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|name| var(name).filter(|value| !value.is_empty()));
+        let unicode = term != "dumb"
+            && match locale {
+                Some(locale) => {
+                    let locale = locale.to_lowercase();
+                    locale.contains("utf-8") || locale.contains("utf8")
+                }
+                // Windows consoles have no locale variables
+                None => cfg!(windows),
+            };
+
+        Capabilities { colors, unicode }
+    }
+
+    /// The closest color the terminal can show, if it can show any.
+    pub fn color(&self, color: Color) -> Option<Color> {
+        match (self.colors, color) {
+            (ColorSupport::None, _) => None,
+            (ColorSupport::TrueColor, _) => Some(color),
+            (ColorSupport::Ansi256, Color::Rgb { r, g, b }) => {
+                Some(Color::AnsiValue(rgb_to_ansi256(r, g, b)))
+            }
+            (ColorSupport::Ansi256, _) => Some(color),
+            (ColorSupport::Basic, Color::Rgb { r, g, b }) => Some(rgb_to_basic(r, g, b)),
+            (ColorSupport::Basic, Color::AnsiValue(value)) => Some(ansi256_to_basic(value)),
+            (ColorSupport::Basic, _) => Some(color),
+        }
+    }
+
+    /// The text with box drawing, arrows and other symbols replaced by ASCII
+    /// when the terminal can't show them.
+    pub fn text<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if self.unicode || text.is_ascii() {
+            return Cow::Borrowed(text);
+        }
+        let mut ascii = String::with_capacity(text.len());
+        for c in text.chars() {
+            match ascii_fallback(c) {
+                Some(fallback) => ascii.push_str(fallback),
+                None => ascii.push(c),
+            }
+        }
+        Cow::Owned(ascii)
+    }
+}
+
+fn ascii_fallback(c: char) -> Option<&'static str> {
+    let fallback = match c {
+        '─' | '━' | '═' => "-",
+        '│' | '┃' | '║' => "|",
+        '┌' | '┐' | '└' | '┘' | '├' | '┤' | '╭' | '╮' | '╰' | '╯' | '╔' | '╗' | '╚' | '╝' | '╠'
+        | '╣' | '┏' | '┓' | '┗' | '┛' | '┣' | '┫' => "+",
+        '↑' => "^",
+        '↓' => "v",
+        '•' => "*",
+        '▇' => "#",
+        '❯' | '›' => ">",
+        '…' => "...",
+        '✓' => "v",
+        '✗' => "x",
+        '·' => "-",
+        _ => return None,
+    };
+    Some(fallback)
+}
+
+// The 16 named colors, by ANSI index
+const BASIC: [Color; 16] = [
+    Color::Black,
+    Color::DarkRed,
+    Color::DarkGreen,
+    Color::DarkYellow,
+    Color::DarkBlue,
+    Color::DarkMagenta,
+    Color::DarkCyan,
+    Color::Grey,
+    Color::DarkGrey,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::White,
+];
+
+fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    // Grays get the 24 step gray ramp, finer than the gray of the cube
+    if r == g && g == b {
+        return match r {
+            0..=7 => 16,
+            249..=255 => 231,
+            _ => 232 + ((r as f32 - 8.0) / 247.0 * 24.0).round() as u8,
+        };
+    }
+    let level = |value: u8| (value as f32 / 255.0 * 5.0).round() as u8;
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+fn rgb_to_basic(r: u8, g: u8, b: u8) -> Color {
+    // Keeps the hue, then picks the bright variant for light colors
+    let brightness = (r.max(g).max(b) as f32 / 127.5).round() as usize;
+    if brightness == 0 {
+        return Color::Black;
+    }
+    let on = |value: u8| (value as f32 / 255.0).round() as usize;
+    let index = on(b) << 2 | on(g) << 1 | on(r);
+    BASIC[index + if brightness == 2 { 8 } else { 0 }]
+}
+
+fn ansi256_to_basic(value: u8) -> Color {
+    match value {
+        0..=15 => BASIC[value as usize],
+        16..=231 => {
+            let level = |index: u8| if index == 0 { 0 } else { 55 + index * 40 };
+            let index = value - 16;
+            rgb_to_basic(level(index / 36), level(index / 6 % 6), level(index % 6))
+        }
+        _ => {
+            let gray = 8 + (value - 232) * 10;
+            rgb_to_basic(gray, gray, gray)
+        }
+    }
+}
+
+static COLOR_CHOICE: Mutex<ColorChoice> = Mutex::new(ColorChoice::Auto);
+
+thread_local! {
+    // The `--color` of the command run by this thread
+    static LOCAL_COLOR_CHOICE: Cell<Option<ColorChoice>> = const { Cell::new(None) };
+}
+
+/// Overrides color detection, as the `--color` flag does.
+pub fn set_color_choice(choice: ColorChoice) {
+    *COLOR_CHOICE.lock().unwrap() = choice;
+}
+
+pub fn color_choice() -> ColorChoice {
+    LOCAL_COLOR_CHOICE
+        .with(Cell::get)
+        .unwrap_or_else(|| *COLOR_CHOICE.lock().unwrap())
+}
+
+// Returns the choice it replaces, so callers can put it back
+pub(crate) fn replace_local_color_choice(choice: Option<ColorChoice>) -> Option<ColorChoice> {
+    LOCAL_COLOR_CHOICE.with(|local| local.replace(choice))
+}
+
+/// Whether the terminal can show more than ASCII, going by the locale.
+pub fn supports_unicode() -> bool {
+    Capabilities::detect(true).unicode
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(choice: ColorChoice, is_terminal: bool, vars: &[(&str, &str)]) -> Capabilities {
+        Capabilities::from_env(choice, is_terminal, |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn test_detects_color_support() {
+        let utf8 = ("LANG", "en_US.UTF-8");
+        let auto = ColorChoice::Auto;
+        let colors = |vars: &[(&str, &str)]| detect(auto, true, vars).colors;
+
+        assert_eq!(
+            colors(&[("COLORTERM", "truecolor")]),
+            ColorSupport::TrueColor
+        );
+        assert_eq!(colors(&[("TERM", "xterm-256color")]), ColorSupport::Ansi256);
+        assert_eq!(colors(&[("TERM", "xterm")]), ColorSupport::Basic);
+        assert_eq!(colors(&[("TERM", "dumb")]), ColorSupport::None);
+        assert_eq!(colors(&[("NO_COLOR", "1")]), ColorSupport::None);
+        assert_eq!(detect(auto, false, &[]).colors, ColorSupport::None);
+        assert_eq!(
+            detect(auto, false, &[("CLICOLOR_FORCE", "1")]).colors,
+            ColorSupport::Basic
+        );
+        assert_eq!(
+            detect(ColorChoice::Always, false, &[("NO_COLOR", "1")]).colors,
+            ColorSupport::Basic
+        );
+        assert_eq!(
+            detect(ColorChoice::Never, true, &[("COLORTERM", "truecolor")]).colors,
+            ColorSupport::None
+        );
+
+        assert!(detect(auto, true, &[utf8]).unicode);
+        assert!(!detect(auto, true, &[utf8, ("LC_ALL", "C")]).unicode);
+    }
+
+    #[test]
+    fn test_downgrades_colors() {
+        let purple = Color::Rgb {
+            r: 122,
+            g: 114,
+            b: 229,
+        };
+        let with = |colors| Capabilities {
+            colors,
+            unicode: true,
+        };
+        assert_eq!(with(ColorSupport::TrueColor).color(purple), Some(purple));
+        assert_eq!(
+            with(ColorSupport::Ansi256).color(purple),
+            Some(Color::AnsiValue(104))
+        );
+        assert_eq!(with(ColorSupport::Basic).color(purple), Some(Color::Blue));
+        assert_eq!(
+            with(ColorSupport::Basic).color(Color::AnsiValue(51)),
+            Some(Color::Cyan)
+        );
+        assert_eq!(with(ColorSupport::None).color(Color::Red), None);
+        assert_eq!(rgb_to_ansi256(128, 128, 128), 244);
+    }
+
+    #[test]
+    fn test_ascii_fallbacks() {
+        let ascii = Capabilities {
+            colors: ColorSupport::None,
+            unicode: false,
+        };
+        assert_eq!(ascii.text("┌──┐ ↑/k up • ❯ é"), "+--+ ^/k up * > é");
+        assert!(matches!(Capabilities::full().text("┌"), Cow::Borrowed(_)));
+    }
+}
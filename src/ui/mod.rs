@@ -0,0 +1,467 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Color;
+use rustubble::list::Item;
+
+mod backend;
+mod clock;
+mod color;
+mod progress;
+mod spinner;
+mod table;
+mod theme;
+mod viewport;
+
+#[cfg(unix)]
+pub(crate) use backend::Redirect;
+pub use backend::{
+    backend, set_backend, set_shared_backend, Backend, CrosstermBackend, SharedBackend, Target,
+};
+pub(crate) use backend::{prefilled_text_area, replace_local_backend};
+pub(crate) use color::replace_local_color_choice;
+pub use color::{color_choice, set_color_choice, supports_unicode, Capabilities, ColorSupport};
+pub(crate) use spinner::spinner_frames;
+pub(crate) use theme::{known_spinner, replace_local_theme};
+pub use theme::{set_theme, theme, Border, BorderStyle, Theme, PRESETS, SPINNER_STYLES};
+
+/// A question for the user, asked through `Backend::prompt`.
+#[derive(Clone, Copy)]
+pub enum Prompt<'a> {
+    /// One line of text, submitted with Enter
+    Input {
+        label: &'a str,
+        placeholder: &'a str,
+        initial_text: &'a str,
+        prefix: &'a str,
+        helper: &'a str,
+    },
+    /// Lines of text, submitted with Tab
+    TextArea {
+        label: &'a str,
+        initial_text: &'a str,
+        visible_lines: usize,
+        helper: &'a str,
+    },
+    /// One of `options`
+    Menu {
+        title: &'a str,
+        subtitle: &'a str,
+        options: &'a [String],
+    },
+    /// The title of one of `items`, which '/' filters
+    List { title: &'a str, items: &'a [Item] },
+}
+
+/// Asks through the current `backend()`. Returns `None` when the user
+/// cancelled.
+pub fn prompt(prompt: &Prompt) -> io::Result<Option<String>> {
+    let backend = backend();
+    let answer = backend.lock().unwrap().prompt(prompt);
+    answer
+}
+
+/// Something shown through `Backend::show`.
+///
+/// Most views show until they end or the user closes them. Live views,
+/// `LiveSpinner` and `LiveProgress`, are drawn once and left on screen while
+/// other work runs, until the next view replaces them or `Backend::clear`.
+#[derive(Clone, Copy)]
+pub enum View<'a> {
+    /// A spinner of a rustubble style, like "Dots2", next to `message`
+    Spinner {
+        message: &'a str,
+        style: &'a str,
+        color: Color,
+    },
+    Table {
+        headers: &'a [String],
+        rows: &'a [Vec<String>],
+    },
+    /// A bar filling up to `progress`, from `start_color` to `end_color`
+    Progress {
+        prefix: &'a str,
+        progress: f32,
+        length: u16,
+        start_color: Color,
+        end_color: Color,
+    },
+    Timer {
+        duration: Duration,
+    },
+    Stopwatch,
+    /// Scrollable text under a header
+    Viewport {
+        header: &'a str,
+        content: &'a str,
+    },
+    /// The `frame`th frame of a spinner, counting from 0
+    LiveSpinner {
+        message: &'a str,
+        style: &'a str,
+        color: Color,
+        frame: usize,
+    },
+    LiveProgress {
+        prefix: &'a str,
+        progress: f32,
+        length: u16,
+        start_color: Color,
+        end_color: Color,
+    },
+}
+
+// How a backend shows a view
+pub(crate) enum Shown {
+    // Drawn once and left on screen
+    Live(Frame),
+    // Run until it ends or is closed
+    Widget(Box<dyn Widget<Output = ()>>),
+}
+
+impl View<'_> {
+    pub(crate) fn shown(&self) -> Shown {
+        let widget: Box<dyn Widget<Output = ()>> = match *self {
+            View::Spinner {
+                message,
+                style,
+                color,
+            } => Box::new(spinner::Spinner::new(message, style, color)),
+            View::Table { headers, rows } => {
+                Box::new(table::Table::new(headers.to_vec(), rows.to_vec(), 1, 10))
+            }
+            View::Progress {
+                prefix,
+                progress,
+                length,
+                start_color,
+                end_color,
+            } => Box::new(
+                progress::ProgressBar::new(prefix, length, start_color, end_color)
+                    .filling_to(progress),
+            ),
+            View::Timer { duration } => Box::new(clock::Timer::new(duration)),
+            View::Stopwatch => Box::new(clock::Stopwatch::new()),
+            // 20 lines of 100 columns
+            View::Viewport { header, content } => {
+                Box::new(viewport::Viewport::new(header, content, 20, 100))
+            }
+            View::LiveSpinner {
+                message,
+                style,
+                color,
+                frame,
+            } => {
+                let spinner = spinner::Spinner::new(message, style, color);
+                return Shown::Live(spinner.render_frame(frame));
+            }
+            View::LiveProgress {
+                prefix,
+                progress,
+                length,
+                start_color,
+                end_color,
+            } => {
+                let mut bar = progress::ProgressBar::new(prefix, length, start_color, end_color);
+                bar.set(progress);
+                return Shown::Live(bar.render());
+            }
+        };
+        Shown::Widget(widget)
+    }
+}
+
+/// Shows through the current `backend()`.
+pub fn show(view: &View) -> io::Result<()> {
+    let backend = backend();
+    let result = backend.lock().unwrap().show(view);
+    result
+}
+
+/// A piece of text with a single style.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Span {
+    pub text: String,
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+}
+
+impl Span {
+    pub fn new(text: impl Into<String>) -> Self {
+        Span {
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn fg(mut self, color: Color) -> Self {
+        self.fg = Some(color);
+        self
+    }
+
+    pub fn bg(mut self, color: Color) -> Self {
+        self.bg = Some(color);
+        self
+    }
+
+    pub fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Line {
+    pub spans: Vec<Span>,
+}
+
+impl Line {
+    pub fn new() -> Self {
+        Line::default()
+    }
+
+    pub fn push(mut self, span: Span) -> Self {
+        self.spans.push(span);
+        self
+    }
+
+    /// The text of the line without its styles.
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+}
+
+impl From<Span> for Line {
+    fn from(span: Span) -> Self {
+        Line { spans: vec![span] }
+    }
+}
+
+/// What a view looks like at one point, as styled lines.
+///
+/// Displays as plain text, which is what tests compare against.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct Frame {
+    pub lines: Vec<Line>,
+}
+
+impl Frame {
+    pub fn new() -> Self {
+        Frame::default()
+    }
+
+    pub fn line(&mut self, line: impl Into<Line>) -> &mut Self {
+        self.lines.push(line.into());
+        self
+    }
+
+    pub fn blank(&mut self) -> &mut Self {
+        self.line(Line::new())
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<String> = self
+            .lines
+            .iter()
+            .map(|line| line.text().trim_end().to_string())
+            .collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// What a widget wants after handling a key.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Step<T> {
+    Continue,
+    Submit(T),
+    Cancel,
+}
+
+/// A view that reacts to keys and draws itself as a `Frame`.
+pub(crate) trait Widget {
+    type Output;
+
+    fn handle_key(&mut self, key: KeyEvent) -> Step<Self::Output>;
+
+    fn render(&self) -> Frame;
+
+    /// How often `tick` runs while no key is pressed, for widgets that change
+    /// with time.
+    fn tick_rate(&self) -> Option<Duration> {
+        None
+    }
+
+    fn tick(&mut self) -> Step<Self::Output> {
+        Step::Continue
+    }
+}
+
+/// Where widgets read their events from.
+pub(crate) trait EventSource {
+    fn read_event(&mut self) -> io::Result<Event>;
+
+    /// Waits up to `timeout` for an event.
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<Option<Event>>;
+}
+
+/// Events typed on the terminal.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct TerminalEvents;
+
+impl EventSource for TerminalEvents {
+    fn read_event(&mut self) -> io::Result<Event> {
+        event::read()
+    }
+
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if event::poll(timeout)? {
+            return event::read().map(Some);
+        }
+        Ok(None)
+    }
+}
+
+// Scripted keys, failing once they run out rather than waiting forever.
+// Widgets that tick keep ticking, as on a terminal nobody types on.
+impl EventSource for VecDeque<KeyEvent> {
+    fn read_event(&mut self) -> io::Result<Event> {
+        self.pop_front()
+            .map(Event::Key)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "ran out of scripted keys"))
+    }
+
+    fn poll_event(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if self.is_empty() {
+            thread::sleep(timeout);
+            return Ok(None);
+        }
+        self.read_event().map(Some)
+    }
+}
+
+/// Draws the widget, then feeds it events until it submits or is cancelled.
+/// Returns `None` when cancelled.
+pub(crate) fn run<W: Widget + ?Sized>(
+    widget: &mut W,
+    events: &mut dyn EventSource,
+    draw: &mut dyn FnMut(&Frame) -> io::Result<()>,
+) -> io::Result<Option<W::Output>> {
+    draw(&widget.render())?;
+    loop {
+        let event = match widget.tick_rate() {
+            Some(rate) => events.poll_event(rate)?,
+            None => Some(events.read_event()?),
+        };
+        let step = match event {
+            Some(Event::Key(key)) if key.kind != KeyEventKind::Release => widget.handle_key(key),
+            Some(_) => continue,
+            None => widget.tick(),
+        };
+        match step {
+            Step::Continue => draw(&widget.render())?,
+            Step::Submit(output) => {
+                draw(&widget.render())?;
+                return Ok(Some(output));
+            }
+            Step::Cancel => return Ok(None),
+        }
+    }
+}
+
+/// Live views shown from another thread, see `show_live`. Stops and clears
+/// them when dropped.
+pub(crate) struct Live {
+    done: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+    backend: SharedBackend,
+}
+
+impl Drop for Live {
+    fn drop(&mut self) {
+        self.done.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        let _ = self.backend.lock().unwrap().clear();
+    }
+}
+
+/// Calls `show` with the current backend and the number of the call every
+/// `interval`, until the returned `Live` is dropped.
+pub(crate) fn show_live(
+    interval: Duration,
+    mut show: impl FnMut(&mut dyn Backend, usize) -> io::Result<()> + Send + 'static,
+) -> Live {
+    let backend = backend();
+    let color = color_choice();
+    let done = Arc::new(AtomicBool::new(false));
+    let thread = {
+        let (backend, done) = (backend.clone(), done.clone());
+        thread::spawn(move || {
+            // Draws with the colors of the run that started it
+            replace_local_color_choice(Some(color));
+            let mut count = 0;
+            while !done.load(Ordering::Relaxed) {
+                let _ = show(&mut *backend.lock().unwrap(), count);
+                count += 1;
+                thread::sleep(interval);
+            }
+        })
+    };
+    Live {
+        done,
+        thread: Some(thread),
+        backend,
+    }
+}
+
+pub(crate) fn is_ctrl_c(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
+// Views close on q or Esc and are cancelled by Ctrl+C
+pub(crate) fn dismiss(key: &KeyEvent) -> Step<()> {
+    match key.code {
+        _ if is_ctrl_c(key) => Step::Cancel,
+        KeyCode::Esc | KeyCode::Char('q') => Step::Submit(()),
+        _ => Step::Continue,
+    }
+}
+
+// The rows of a `len` long list to show so that `selected` stays visible
+pub(crate) fn window(len: usize, selected: usize, size: usize) -> Range<usize> {
+    let start = (selected + 1).saturating_sub(size);
+    start..len.min(start + size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_displays_as_plain_text() {
+        let mut frame = Frame::new();
+        frame
+            .line(Span::new("Name").bold())
+            .blank()
+            .line(Line::new().push(Span::new("> ")).push(Span::new("abc  ")));
+        assert_eq!(frame.to_string(), "Name\n\n> abc");
+    }
+
+    #[test]
+    fn test_window_follows_selection() {
+        assert_eq!(window(3, 0, 5), 0..3);
+        assert_eq!(window(10, 2, 5), 0..5);
+        assert_eq!(window(10, 7, 5), 3..8);
+    }
+}
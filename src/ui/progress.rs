@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+use crossterm::event::KeyEvent;
+use crossterm::style::Color;
+use rustubble::colors::blend_color;
+
+use super::{dismiss, Frame, Line, Span, Step, Widget};
+
+// How much a filling bar grows on each tick
+const STEP: f32 = 0.01;
+const TICK: Duration = Duration::from_millis(10);
+
+/// A bar filling with a gradient from `start_color` to `end_color`.
+#[derive(Clone, Debug)]
+pub(crate) struct ProgressBar {
+    prefix: String,
+    progress: f32,
+    length: u16,
+    start_color: Color,
+    end_color: Color,
+    // Where the bar stops filling as a widget
+    target: f32,
+}
+
+impl ProgressBar {
+    pub fn new(prefix: &str, length: u16, start_color: Color, end_color: Color) -> Self {
+        ProgressBar {
+            prefix: prefix.to_string(),
+            progress: 0.0,
+            length,
+            start_color,
+            end_color,
+            target: 0.0,
+        }
+    }
+
+    /// As a widget, fills up from empty to `progress` and ends there.
+    pub fn filling_to(mut self, progress: f32) -> Self {
+        self.target = progress.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the progress, from 0.0 to 1.0.
+    pub fn set(&mut self, progress: f32) {
+        self.progress = progress.clamp(0.0, 1.0);
+    }
+
+    pub fn render(&self) -> Frame {
+        let mut line = Line::new();
+        if !self.prefix.is_empty() {
+            line = line.push(Span::new(format!("{} ", self.prefix)));
+        }
+        for cell in 0..self.length {
+            let ratio = cell as f32 / self.length as f32;
+            let color = if ratio < self.progress {
+                blend_color(self.start_color, self.end_color, ratio)
+            } else {
+                Color::DarkGrey
+            };
+            line = line.push(Span::new("▇").fg(color));
+        }
+        line = line.push(Span::new(format!(" {:.0}%", self.progress * 100.0)));
+
+        let mut frame = Frame::new();
+        frame.line(line);
+        frame
+    }
+}
+
+impl Widget for ProgressBar {
+    type Output = ();
+
+    fn handle_key(&mut self, key: KeyEvent) -> Step<()> {
+        dismiss(&key)
+    }
+
+    fn render(&self) -> Frame {
+        ProgressBar::render(self)
+    }
+
+    fn tick_rate(&self) -> Option<Duration> {
+        Some(TICK)
+    }
+
+    fn tick(&mut self) -> Step<()> {
+        if self.progress >= self.target {
+            return Step::Submit(());
+        }
+        self.set((self.progress + STEP).min(self.target));
+        Step::Continue
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_bar_fills_with_a_gradient() {
+        let mut bar = ProgressBar::new("Copying", 4, Color::Red, Color::Blue);
+        bar.set(0.5);
+        let frame = bar.render();
+        assert_eq!(frame.to_string(), "Copying ▇▇▇▇ 50%");
+
+        let colors: Vec<_> = frame.lines[0].spans.iter().map(|span| span.fg).collect();
+        assert_eq!(colors[3..5], [Some(Color::DarkGrey); 2]);
+        assert_ne!(colors[1], Some(Color::DarkGrey));
+    }
+}
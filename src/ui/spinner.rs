@@ -0,0 +1,183 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::KeyEvent;
+use crossterm::style::Color;
+
+use super::{dismiss, theme, Frame, Line, Span, Step, Widget};
+
+// Name, milliseconds per frame and frames of every style in
+// `SPINNER_STYLES`, as rustubble draws them
+#[rustfmt::skip]
+const SPINNERS: [(&str, u64, &[&str]); 81] = [
+    ("Dots2", 80, &["⣾", "⣽", "⣻", "⢿", "⡿", "⣟", "⣯", "⣷"]),
+    ("Dots3", 80, &["⠋", "⠙", "⠚", "⠞", "⠖", "⠦", "⠴", "⠲", "⠳", "⠓"]),
+    ("Dots4", 80, &["⠄", "⠆", "⠇", "⠋", "⠙", "⠸", "⠰", "⠠", "⠰", "⠸", "⠙", "⠋", "⠇", "⠆"]),
+    ("Dots5", 80, &["⠋", "⠙", "⠚", "⠒", "⠂", "⠂", "⠒", "⠲", "⠴", "⠦", "⠖", "⠒", "⠐", "⠐", "⠒", "⠓", "⠋"]),
+    ("Dots6", 80, &["⠁", "⠉", "⠙", "⠚", "⠒", "⠂", "⠂", "⠒", "⠲", "⠴", "⠤", "⠄", "⠄", "⠤", "⠴", "⠲", "⠒", "⠂", "⠂", "⠒", "⠚", "⠙", "⠉", "⠁"]),
+    ("Dots7", 80, &["⠈", "⠉", "⠋", "⠓", "⠒", "⠐", "⠐", "⠒", "⠖", "⠦", "⠤", "⠠", "⠠", "⠤", "⠦", "⠖", "⠒", "⠐", "⠐", "⠒", "⠓", "⠋", "⠉", "⠈"]),
+    ("Dots8", 80, &["⠁", "⠁", "⠉", "⠙", "⠚", "⠒", "⠂", "⠂", "⠒", "⠲", "⠴", "⠤", "⠄", "⠄", "⠤", "⠠", "⠠", "⠤", "⠦", "⠖", "⠒", "⠐", "⠐", "⠒", "⠓", "⠋", "⠉", "⠈", "⠈"]),
+    ("Dots9", 80, &["⢹", "⢺", "⢼", "⣸", "⣇", "⡧", "⡗", "⡏"]),
+    ("Dots10", 80, &["⢄", "⢂", "⢁", "⡁", "⡈", "⡐", "⡠"]),
+    ("Dots11", 100, &["⠁", "⠂", "⠄", "⡀", "⢀", "⠠", "⠐", "⠈"]),
+    ("Dots12", 80, &["⢀⠀", "⡀⠀", "⠄⠀", "⢂⠀", "⡂⠀", "⠅⠀", "⢃⠀", "⡃⠀", "⠍⠀", "⢋⠀", "⡋⠀", "⠍⠁", "⢋⠁", "⡋⠁", "⠍⠉", "⠋⠉", "⠋⠉", "⠉⠙", "⠉⠙", "⠉⠩", "⠈⢙", "⠈⡙", "⢈⠩", "⡀⢙", "⠄⡙", "⢂⠩", "⡂⢘", "⠅⡘", "⢃⠨", "⡃⢐", "⠍⡐", "⢋⠠", "⡋⢀", "⠍⡁", "⢋⠁", "⡋⠁", "⠍⠉", "⠋⠉", "⠋⠉", "⠉⠙", "⠉⠙", "⠉⠩", "⠈⢙", "⠈⡙", "⠈⠩", "⠀⢙", "⠀⡙", "⠀⠩", "⠀⢘", "⠀⡘", "⠀⠨", "⠀⢐", "⠀⡐", "⠀⠠", "⠀⢀", "⠀⡀"]),
+    ("Dots8Bit", 80, &["⠀", "⠁", "⠂", "⠃", "⠄", "⠅", "⠆", "⠇", "⡀", "⡁", "⡂", "⡃", "⡄", "⡅", "⡆", "⡇", "⠈", "⠉", "⠊", "⠋", "⠌", "⠍", "⠎", "⠏", "⡈", "⡉", "⡊", "⡋", "⡌", "⡍", "⡎", "⡏", "⠐", "⠑", "⠒", "⠓", "⠔", "⠕", "⠖", "⠗", "⡐", "⡑", "⡒", "⡓", "⡔", "⡕", "⡖", "⡗", "⠘", "⠙", "⠚", "⠛", "⠜", "⠝", "⠞", "⠟", "⡘", "⡙", "⡚", "⡛", "⡜", "⡝", "⡞", "⡟", "⠠", "⠡", "⠢", "⠣", "⠤", "⠥", "⠦", "⠧", "⡠", "⡡", "⡢", "⡣", "⡤", "⡥", "⡦", "⡧", "⠨", "⠩", "⠪", "⠫", "⠬", "⠭", "⠮", "⠯", "⡨", "⡩", "⡪", "⡫", "⡬", "⡭", "⡮", "⡯", "⠰", "⠱", "⠲", "⠳", "⠴", "⠵", "⠶", "⠷", "⡰", "⡱", "⡲", "⡳", "⡴", "⡵", "⡶", "⡷", "⠸", "⠹", "⠺", "⠻", "⠼", "⠽", "⠾", "⠿", "⡸", "⡹", "⡺", "⡻", "⡼", "⡽", "⡾", "⡿", "⢀", "⢁", "⢂", "⢃", "⢄", "⢅", "⢆", "⢇", "⣀", "⣁", "⣂", "⣃", "⣄", "⣅", "⣆", "⣇", "⢈", "⢉", "⢊", "⢋", "⢌", "⢍", "⢎", "⢏", "⣈", "⣉", "⣊", "⣋", "⣌", "⣍", "⣎", "⣏", "⢐", "⢑", "⢒", "⢓", "⢔", "⢕", "⢖", "⢗", "⣐", "⣑", "⣒", "⣓", "⣔", "⣕", "⣖", "⣗", "⢘", "⢙", "⢚", "⢛", "⢜", "⢝", "⢞", "⢟", "⣘", "⣙", "⣚", "⣛", "⣜", "⣝", "⣞", "⣟", "⢠", "⢡", "⢢", "⢣", "⢤", "⢥", "⢦", "⢧", "⣠", "⣡", "⣢", "⣣", "⣤", "⣥", "⣦", "⣧", "⢨", "⢩", "⢪", "⢫", "⢬", "⢭", "⢮", "⢯", "⣨", "⣩", "⣪", "⣫", "⣬", "⣭", "⣮", "⣯", "⢰", "⢱", "⢲", "⢳", "⢴", "⢵", "⢶", "⢷", "⣰", "⣱", "⣲", "⣳", "⣴", "⣵", "⣶", "⣷", "⢸", "⢹", "⢺", "⢻", "⢼", "⢽", "⢾", "⢿", "⣸", "⣹", "⣺", "⣻", "⣼", "⣽", "⣾", "⣿"]),
+    ("Line", 130, &["-", "\\", "|", "/"]),
+    ("Line2", 100, &["⠂", "-", "–", "—", "–", "-"]),
+    ("Pipe", 100, &["┤", "┘", "┴", "└", "├", "┌", "┬", "┐"]),
+    ("SimpleDots", 400, &[".  ", ".. ", "...", "   "]),
+    ("SimpleDotsScrolling", 200, &[".  ", ".. ", "...", " ..", "  .", "   "]),
+    ("Star", 70, &["✶", "✸", "✹", "✺", "✹", "✷"]),
+    ("Star2", 80, &["+", "x", "*"]),
+    ("Flip", 70, &["_", "_", "_", "-", "`", "`", "'", "´", "-", "_", "_", "_"]),
+    ("Hamburger", 100, &["☱", "☲", "☴"]),
+    ("GrowVertical", 120, &["▁", "▃", "▄", "▅", "▆", "▇", "▆", "▅", "▄", "▃"]),
+    ("GrowHorizontal", 120, &["▏", "▎", "▍", "▌", "▋", "▊", "▉", "▊", "▋", "▌", "▍", "▎"]),
+    ("Balloon", 140, &[" ", ".", "o", "O", "@", "*", " "]),
+    ("Balloon2", 120, &[".", "o", "O", "°", "O", "o", "."]),
+    ("Noise", 100, &["▓", "▒", "░"]),
+    ("Bounce", 120, &["⠁", "⠂", "⠄", "⠂"]),
+    ("BoxBounce", 120, &["▖", "▘", "▝", "▗"]),
+    ("BoxBounce2", 100, &["▌", "▀", "▐", "▄"]),
+    ("Triangle", 50, &["◢", "◣", "◤", "◥"]),
+    ("Arc", 100, &["◜", "◠", "◝", "◞", "◡", "◟"]),
+    ("Circle", 120, &["◡", "⊙", "◠"]),
+    ("SquareCorners", 180, &["◰", "◳", "◲", "◱"]),
+    ("CircleQuarters", 120, &["◴", "◷", "◶", "◵"]),
+    ("CircleHalves", 50, &["◐", "◓", "◑", "◒"]),
+    ("Squish", 100, &["╫", "╪"]),
+    ("Toggle", 250, &["⊶", "⊷"]),
+    ("Toggle2", 80, &["▫", "▪"]),
+    ("Toggle3", 120, &["□", "■"]),
+    ("Toggle4", 100, &["■", "□", "▪", "▫"]),
+    ("Toggle5", 100, &["▮", "▯"]),
+    ("Toggle6", 300, &["ဝ", "၀"]),
+    ("Toggle7", 80, &["⦾", "⦿"]),
+    ("Toggle8", 100, &["◍", "◌"]),
+    ("Toggle9", 100, &["◉", "◎"]),
+    ("Toggle10", 100, &["㊂", "㊀", "㊁"]),
+    ("Toggle11", 50, &["⧇", "⧆"]),
+    ("Toggle12", 120, &["☗", "☖"]),
+    ("Toggle13", 80, &["=", "*", "-"]),
+    ("Arrow", 100, &["←", "↖", "↑", "↗", "→", "↘", "↓", "↙"]),
+    ("Arrow2", 80, &["⬆️ ", "↗️ ", "➡️ ", "↘️ ", "⬇️ ", "↙️ ", "⬅️ ", "↖️ "]),
+    ("Arrow3", 120, &["▹▹▹▹▹", "▸▹▹▹▹", "▹▸▹▹▹", "▹▹▸▹▹", "▹▹▹▸▹", "▹▹▹▹▸"]),
+    ("BouncingBar", 80, &["[    ]", "[=   ]", "[==  ]", "[=== ]", "[ ===]", "[  ==]", "[   =]", "[    ]", "[   =]", "[  ==]", "[ ===]", "[====]", "[=== ]", "[==  ]", "[=   ]"]),
+    ("BouncingBall", 80, &["( ●    )", "(  ●   )", "(   ●  )", "(    ● )", "(     ●)", "(    ● )", "(   ●  )", "(  ●   )", "( ●    )", "(●     )"]),
+    ("Smiley", 200, &["😄 ", "😝 "]),
+    ("Monkey", 300, &["🙈 ", "🙈 ", "🙉 ", "🙊 "]),
+    ("Hearts", 100, &["💛 ", "💙 ", "💜 ", "💚 ", "❤️ "]),
+    ("Clock", 100, &["🕛 ", "🕐 ", "🕑 ", "🕒 ", "🕓 ", "🕔 ", "🕕 ", "🕖 ", "🕗 ", "🕘 ", "🕙 ", "🕚 "]),
+    ("Earth", 180, &["🌍 ", "🌎 ", "🌏 "]),
+    ("Material", 17, &["█▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁", "██▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁", "███▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁", "████▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁", "██████▁▁▁▁▁▁▁▁▁▁▁▁▁▁", "██████▁▁▁▁▁▁▁▁▁▁▁▁▁▁", "███████▁▁▁▁▁▁▁▁▁▁▁▁▁", "████████▁▁▁▁▁▁▁▁▁▁▁▁", "█████████▁▁▁▁▁▁▁▁▁▁▁", "█████████▁▁▁▁▁▁▁▁▁▁▁", "██████████▁▁▁▁▁▁▁▁▁▁", "███████████▁▁▁▁▁▁▁▁▁", "█████████████▁▁▁▁▁▁▁", "██████████████▁▁▁▁▁▁", "██████████████▁▁▁▁▁▁", "▁██████████████▁▁▁▁▁", "▁██████████████▁▁▁▁▁", "▁██████████████▁▁▁▁▁", "▁▁██████████████▁▁▁▁", "▁▁▁██████████████▁▁▁", "▁▁▁▁█████████████▁▁▁", "▁▁▁▁██████████████▁▁", "▁▁▁▁██████████████▁▁", "▁▁▁▁▁██████████████▁", "▁▁▁▁▁██████████████▁", "▁▁▁▁▁██████████████▁", "▁▁▁▁▁▁██████████████", "▁▁▁▁▁▁██████████████", "▁▁▁▁▁▁▁█████████████", "▁▁▁▁▁▁▁█████████████", "▁▁▁▁▁▁▁▁████████████", "▁▁▁▁▁▁▁▁████████████", "▁▁▁▁▁▁▁▁▁███████████", "▁▁▁▁▁▁▁▁▁███████████", "▁▁▁▁▁▁▁▁▁▁██████████", "▁▁▁▁▁▁▁▁▁▁██████████", "▁▁▁▁▁▁▁▁▁▁▁▁████████", "▁▁▁▁▁▁▁▁▁▁▁▁▁███████", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁██████", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁█████", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁█████", "█▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁████", "██▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁███", "██▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁███", "███▁▁▁▁▁▁▁▁▁▁▁▁▁▁███", "████▁▁▁▁▁▁▁▁▁▁▁▁▁▁██", "█████▁▁▁▁▁▁▁▁▁▁▁▁▁▁█", "█████▁▁▁▁▁▁▁▁▁▁▁▁▁▁█", "██████▁▁▁▁▁▁▁▁▁▁▁▁▁█", "████████▁▁▁▁▁▁▁▁▁▁▁▁", "█████████▁▁▁▁▁▁▁▁▁▁▁", "█████████▁▁▁▁▁▁▁▁▁▁▁", "█████████▁▁▁▁▁▁▁▁▁▁▁", "█████████▁▁▁▁▁▁▁▁▁▁▁", "███████████▁▁▁▁▁▁▁▁▁", "████████████▁▁▁▁▁▁▁▁", "████████████▁▁▁▁▁▁▁▁", "██████████████▁▁▁▁▁▁", "██████████████▁▁▁▁▁▁", "▁██████████████▁▁▁▁▁", "▁██████████████▁▁▁▁▁", "▁▁▁█████████████▁▁▁▁", "▁▁▁▁▁████████████▁▁▁", "▁▁▁▁▁████████████▁▁▁", "▁▁▁▁▁▁███████████▁▁▁", "▁▁▁▁▁▁▁▁█████████▁▁▁", "▁▁▁▁▁▁▁▁█████████▁▁▁", "▁▁▁▁▁▁▁▁▁█████████▁▁", "▁▁▁▁▁▁▁▁▁█████████▁▁", "▁▁▁▁▁▁▁▁▁▁█████████▁", "▁▁▁▁▁▁▁▁▁▁▁████████▁", "▁▁▁▁▁▁▁▁▁▁▁████████▁", "▁▁▁▁▁▁▁▁▁▁▁▁███████▁", "▁▁▁▁▁▁▁▁▁▁▁▁███████▁", "▁▁▁▁▁▁▁▁▁▁▁▁▁███████", "▁▁▁▁▁▁▁▁▁▁▁▁▁███████", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁█████", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁████", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁████", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁████", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁███", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁███", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁██", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁██", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁██", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁█", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁█", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁█", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁", "▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁▁"]),
+    ("Moon", 80, &["🌑 ", "🌒 ", "🌓 ", "🌔 ", "🌕 ", "🌖 ", "🌗 ", "🌘 "]),
+    ("Runner", 140, &["🚶 ", "🏃 "]),
+    ("Pong", 80, &["▐⠂       ▌", "▐⠈       ▌", "▐ ⠂      ▌", "▐ ⠠      ▌", "▐  ⡀     ▌", "▐  ⠠     ▌", "▐   ⠂    ▌", "▐   ⠈    ▌", "▐    ⠂   ▌", "▐    ⠠   ▌", "▐     ⡀  ▌", "▐     ⠠  ▌", "▐      ⠂ ▌", "▐      ⠈ ▌", "▐       ⠂▌", "▐       ⠠▌", "▐       ⡀▌", "▐      ⠠ ▌", "▐      ⠂ ▌", "▐     ⠈  ▌", "▐     ⠂  ▌", "▐    ⠠   ▌", "▐    ⡀   ▌", "▐   ⠠    ▌", "▐   ⠂    ▌", "▐  ⠈     ▌", "▐  ⠂     ▌", "▐ ⠠      ▌", "▐ ⡀      ▌", "▐⠠       ▌"]),
+    ("Shark", 120, &["▐|\\____________▌", "▐_|\\___________▌", "▐__|\\__________▌", "▐___|\\_________▌", "▐____|\\________▌", "▐_____|\\_______▌", "▐______|\\______▌", "▐_______|\\_____▌", "▐________|\\____▌", "▐_________|\\___▌", "▐__________|\\__▌", "▐___________|\\_▌", "▐____________|\\▌", "▐____________/|▌", "▐___________/|_▌", "▐__________/|__▌", "▐_________/|___▌", "▐________/|____▌", "▐_______/|_____▌", "▐______/|______▌", "▐_____/|_______▌", "▐____/|________▌", "▐___/|_________▌", "▐__/|__________▌", "▐_/|___________▌", "▐/|____________▌"]),
+    ("Dqpb", 100, &["d", "q", "p", "b"]),
+    ("Weather", 100, &["☀️ ", "☀️ ", "☀️ ", "🌤 ", "⛅️ ", "🌥 ", "☁️ ", "🌧 ", "🌨 ", "🌧 ", "🌨 ", "🌧 ", "🌨 ", "⛈ ", "🌨 ", "🌧 ", "🌨 ", "☁️ ", "🌥 ", "⛅️ ", "🌤 ", "☀️ ", "☀️ "]),
+    ("Christmas", 400, &["🌲", "🎄"]),
+    ("Grenade", 80, &["،  ", "′  ", " ´ ", " ‾ ", "  ⸌", "  ⸊", "  |", "  ⁎", "  ⁕", " ෴ ", "  ⁓", "   ", "   ", "   "]),
+    ("Point", 125, &["∙∙∙", "●∙∙", "∙●∙", "∙∙●", "∙∙∙"]),
+    ("Layer", 150, &["-", "=", "≡"]),
+    ("BetaWave", 80, &["ρββββββ", "βρβββββ", "ββρββββ", "βββρβββ", "ββββρββ", "βββββρβ", "ββββββρ"]),
+    ("FingerDance", 160, &["🤘 ", "🤟 ", "🖖 ", "✋ ", "🤚 ", "👆 "]),
+    ("FistBump", 80, &["🤜　　　　🤛 ", "🤜　　　　🤛 ", "🤜　　　　🤛 ", "　🤜　　🤛　 ", "　　🤜🤛　　 ", "　🤜✨🤛　　 ", "🤜　✨　🤛　 "]),
+    ("SoccerHeader", 80, &[" 🧑⚽️       🧑 ", "🧑  ⚽️      🧑 ", "🧑   ⚽️     🧑 ", "🧑    ⚽️    🧑 ", "🧑     ⚽️   🧑 ", "🧑      ⚽️  🧑 ", "🧑       ⚽️🧑  ", "🧑      ⚽️  🧑 ", "🧑     ⚽️   🧑 ", "🧑    ⚽️    🧑 ", "🧑   ⚽️     🧑 ", "🧑  ⚽️      🧑 "]),
+    ("Mindblown", 160, &["😐 ", "😐 ", "😮 ", "😮 ", "😦 ", "😦 ", "😧 ", "😧 ", "🤯 ", "💥 ", "✨ ", "　 ", "　 ", "　 "]),
+    ("Speaker", 160, &["🔈 ", "🔉 ", "🔊 ", "🔉 "]),
+    ("OrangePulse", 100, &["🔸 ", "🔶 ", "🟠 ", "🟠 ", "🔶 "]),
+    ("BluePulse", 100, &["🔹 ", "🔷 ", "🔵 ", "🔵 ", "🔷 "]),
+    ("OrangeBluePulse", 100, &["🔸 ", "🔶 ", "🟠 ", "🟠 ", "🔶 ", "🔹 ", "🔷 ", "🔵 ", "🔵 ", "🔷 "]),
+    ("TimeTravel", 100, &["🕛 ", "🕚 ", "🕙 ", "🕘 ", "🕗 ", "🕖 ", "🕕 ", "🕔 ", "🕓 ", "🕒 ", "🕑 ", "🕐 "]),
+    ("Aesthetic", 80, &["▰▱▱▱▱▱▱", "▰▰▱▱▱▱▱", "▰▰▰▱▱▱▱", "▰▰▰▰▱▱▱", "▰▰▰▰▰▱▱", "▰▰▰▰▰▰▱", "▰▰▰▰▰▰▰", "▰▱▱▱▱▱▱"]),
+];
+
+/// The frames and frame interval of a spinner style like "Dots2", ignoring
+/// case.
+pub(crate) fn spinner_frames(style: &str) -> Option<(&'static [&'static str], Duration)> {
+    SPINNERS
+        .iter()
+        .find(|(name, _, _)| name.eq_ignore_ascii_case(style))
+        .map(|(_, interval, frames)| (*frames, Duration::from_millis(*interval)))
+}
+
+/// An animated spinner next to a message, until q or Esc.
+#[derive(Clone, Debug)]
+pub(crate) struct Spinner {
+    message: String,
+    frames: &'static [&'static str],
+    interval: Duration,
+    color: Color,
+    started: Instant,
+}
+
+impl Spinner {
+    /// A spinner of `style`, or of the style of the theme if it is unknown.
+    pub fn new(message: &str, style: &str, color: Color) -> Self {
+        let (frames, interval) = spinner_frames(style)
+            .or_else(|| spinner_frames(&theme().spinner))
+            .unwrap_or((&["-", "\\", "|", "/"], Duration::from_millis(130)));
+        Spinner {
+            message: message.to_string(),
+            frames,
+            interval,
+            color,
+            started: Instant::now(),
+        }
+    }
+
+    /// The `index`th frame, wrapping around.
+    pub fn render_frame(&self, index: usize) -> Frame {
+        let mut frame = Frame::new();
+        frame.line(
+            Line::new()
+                .push(Span::new(self.frames[index % self.frames.len()]).fg(self.color))
+                .push(Span::new(format!(" {}", self.message))),
+        );
+        frame
+    }
+}
+
+impl Widget for Spinner {
+    type Output = ();
+
+    fn handle_key(&mut self, key: KeyEvent) -> Step<()> {
+        dismiss(&key)
+    }
+
+    // The frame to show now, going by the time since the spinner started
+    fn render(&self) -> Frame {
+        let elapsed = self.started.elapsed().as_millis() / self.interval.as_millis().max(1);
+        self.render_frame(elapsed as usize)
+    }
+
+    fn tick_rate(&self) -> Option<Duration> {
+        Some(self.interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::SPINNER_STYLES;
+
+    #[test]
+    fn test_every_style_has_frames() {
+        assert!(SPINNER_STYLES
+            .iter()
+            .all(|style| spinner_frames(style).is_some_and(|(frames, _)| !frames.is_empty())));
+        assert_eq!(spinner_frames("line").unwrap().0, ["-", "\\", "|", "/"]);
+
+        let spinner = Spinner::new("Loading", "nope", Color::Cyan);
+        assert_eq!(
+            spinner.interval,
+            spinner_frames(&theme().spinner).unwrap().1
+        );
+        assert_eq!(
+            Spinner::new("Loading", "Line", Color::Cyan)
+                .render_frame(5)
+                .to_string(),
+            "\\ Loading"
+        );
+    }
+}
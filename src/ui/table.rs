@@ -0,0 +1,142 @@
+use super::{dismiss, theme, window, Frame, Line, Span, Step, Theme, Widget};
+use crossterm::event::{KeyCode, KeyEvent};
+
+/// Rows under headers, browsed with the arrow keys until q, Esc or Enter.
+#[derive(Clone, Debug)]
+pub(crate) struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+    selected: usize,
+    // Spaces on each side of a cell
+    padding: usize,
+    visible_rows: usize,
+    theme: Theme,
+}
+
+impl Table {
+    pub fn new(
+        headers: Vec<String>,
+        rows: Vec<Vec<String>>,
+        padding: usize,
+        visible_rows: usize,
+    ) -> Self {
+        Table {
+            headers,
+            rows,
+            selected: 0,
+            padding,
+            visible_rows: visible_rows.max(1),
+            theme: theme(),
+        }
+    }
+
+    fn column_widths(&self) -> Vec<usize> {
+        (0..self.headers.len())
+            .map(|column| {
+                let cells = self.rows.iter().filter_map(|row| row.get(column));
+                cells
+                    .chain([&self.headers[column]])
+                    .map(|cell| cell.chars().count())
+                    .max()
+                    .unwrap_or_default()
+                    + 2 * self.padding
+            })
+            .collect()
+    }
+
+    fn row_line(&self, cells: &[String], widths: &[usize], selected: bool) -> Line {
+        let bar = self.theme.border.glyphs().vertical;
+        let mut line = Line::new().push(Span::new(bar));
+        for (column, width) in widths.iter().enumerate() {
+            let cell = cells.get(column).map(String::as_str).unwrap_or_default();
+            let text = format!("{:^width$}", cell, width = width);
+            line = line.push(if selected {
+                Span::new(text).bg(self.theme.primary)
+            } else {
+                Span::new(text)
+            });
+        }
+        line.push(Span::new(bar))
+    }
+}
+
+impl Widget for Table {
+    type Output = ();
+
+    fn handle_key(&mut self, key: KeyEvent) -> Step<()> {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.selected = (self.selected + 1).min(self.rows.len().saturating_sub(1))
+            }
+            KeyCode::Enter => return Step::Submit(()),
+            _ => return dismiss(&key),
+        }
+        Step::Continue
+    }
+
+    fn render(&self) -> Frame {
+        let widths = self.column_widths();
+        let border = self.theme.border.glyphs();
+        let rule = border.horizontal.repeat(widths.iter().sum());
+        let mut frame = Frame::new();
+        frame
+            .line(Span::new(
+                border.top_left.to_string() + &rule + border.top_right,
+            ))
+            .line(
+                self.row_line(&self.headers, &widths, false)
+                    .spans
+                    .into_iter()
+                    .map(Span::bold)
+                    .fold(Line::new(), Line::push),
+            )
+            .line(Span::new(
+                border.left_tee.to_string() + &rule + border.right_tee,
+            ));
+        for index in window(self.rows.len(), self.selected, self.visible_rows) {
+            frame.line(self.row_line(&self.rows[index], &widths, index == self.selected));
+        }
+        frame
+            .line(Span::new(
+                border.bottom_left.to_string() + &rule + border.bottom_right,
+            ))
+            .line(Span::new("↑/k up • ↓/j down • q quit").fg(self.theme.muted));
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_layout() {
+        let mut table = Table::new(
+            vec!["ID".to_string(), "Name".to_string()],
+            vec![
+                vec!["1".to_string(), "Ferris".to_string()],
+                vec!["2".to_string(), "Corro".to_string()],
+            ],
+            1,
+            5,
+        );
+        table.theme = Theme::default();
+        assert_eq!(
+            table.render().to_string(),
+            "┌────────────┐\n\
+             │ ID   Name  │\n\
+             ├────────────┤\n\
+             │ 1   Ferris │\n\
+             │ 2   Corro  │\n\
+             └────────────┘\n\
+             ↑/k up • ↓/j down • q quit"
+        );
+
+        table.theme = Theme::preset("gruvbox").unwrap();
+        assert!(table
+            .render()
+            .to_string()
+            .starts_with("┏━━━━━━━━━━━━┓\n┃ ID"));
+    }
+}
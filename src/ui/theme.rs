@@ -0,0 +1,419 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crossterm::style::Color;
+use rustubble::colors::custom::{CYAN, DARK_WHITE, ORANGE, PURPLE};
+use serde::Deserialize;
+
+use super::supports_unicode;
+use crate::settings::Format;
+
+/// The names `Theme::preset` knows.
+pub const PRESETS: [&str; 4] = ["default", "dracula", "nord", "gruvbox"];
+
+/// The spinner styles of rustubble.
+pub const SPINNER_STYLES: [&str; 81] = [
+    "Dots2",
+    "Dots3",
+    "Dots4",
+    "Dots5",
+    "Dots6",
+    "Dots7",
+    "Dots8",
+    "Dots9",
+    "Dots10",
+    "Dots11",
+    "Dots12",
+    "Dots8Bit",
+    "Line",
+    "Line2",
+    "Pipe",
+    "SimpleDots",
+    "SimpleDotsScrolling",
+    "Star",
+    "Star2",
+    "Flip",
+    "Hamburger",
+    "GrowVertical",
+    "GrowHorizontal",
+    "Balloon",
+    "Balloon2",
+    "Noise",
+    "Bounce",
+    "BoxBounce",
+    "BoxBounce2",
+    "Triangle",
+    "Arc",
+    "Circle",
+    "SquareCorners",
+    "CircleQuarters",
+    "CircleHalves",
+    "Squish",
+    "Toggle",
+    "Toggle2",
+    "Toggle3",
+    "Toggle4",
+    "Toggle5",
+    "Toggle6",
+    "Toggle7",
+    "Toggle8",
+    "Toggle9",
+    "Toggle10",
+    "Toggle11",
+    "Toggle12",
+    "Toggle13",
+    "Arrow",
+    "Arrow2",
+    "Arrow3",
+    "BouncingBar",
+    "BouncingBall",
+    "Smiley",
+    "Monkey",
+    "Hearts",
+    "Clock",
+    "Earth",
+    "Material",
+    "Moon",
+    "Runner",
+    "Pong",
+    "Shark",
+    "Dqpb",
+    "Weather",
+    "Christmas",
+    "Grenade",
+    "Point",
+    "Layer",
+    "BetaWave",
+    "FingerDance",
+    "FistBump",
+    "SoccerHeader",
+    "Mindblown",
+    "Speaker",
+    "OrangePulse",
+    "BluePulse",
+    "OrangeBluePulse",
+    "TimeTravel",
+    "Aesthetic",
+];
+
+// Styles whose frames are all ASCII
+const ASCII_SPINNERS: [&str; 3] = ["Line", "SimpleDots", "SimpleDotsScrolling"];
+
+/// The name of a spinner style as rustubble spells it, ignoring case.
+pub(crate) fn known_spinner(style: &str) -> Option<&'static str> {
+    SPINNER_STYLES
+        .iter()
+        .find(|name| name.eq_ignore_ascii_case(style))
+        .copied()
+}
+
+/// Colors and glyphs shared by views, messages and help. The rustubble
+/// prompts only take the prompt prefix.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    /// Titles, headers and selections
+    pub primary: Color,
+    /// Spinners and highlights
+    pub accent: Color,
+    /// Errors and failures
+    pub error: Color,
+    pub warning: Color,
+    pub success: Color,
+    /// Placeholders, help lines and other secondary text
+    pub muted: Color,
+    /// Shown before the text of a prompt
+    pub prompt_prefix: String,
+    /// Drawn around tables and the header of viewports
+    pub border: BorderStyle,
+    /// One of `SPINNER_STYLES`
+    pub spinner: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            primary: PURPLE,
+            accent: CYAN,
+            error: rgb(255, 85, 85),
+            warning: ORANGE,
+            success: rgb(80, 220, 120),
+            muted: DARK_WHITE,
+            prompt_prefix: ">".to_string(),
+            border: BorderStyle::Sharp,
+            spinner: "Dots2".to_string(),
+        }
+    }
+}
+
+impl Theme {
+    /// A built-in theme, see `PRESETS`.
+    pub fn preset(name: &str) -> Option<Theme> {
+        let theme = match name.to_lowercase().as_str() {
+            "default" => Theme::default(),
+            "dracula" => Theme {
+                primary: rgb(189, 147, 249),
+                accent: rgb(139, 233, 253),
+                error: rgb(255, 85, 85),
+                warning: rgb(241, 250, 140),
+                success: rgb(80, 250, 123),
+                muted: rgb(98, 114, 164),
+                prompt_prefix: "❯".to_string(),
+                border: BorderStyle::Rounded,
+                spinner: "Dots3".to_string(),
+            },
+            "nord" => Theme {
+                primary: rgb(136, 192, 208),
+                accent: rgb(163, 190, 140),
+                error: rgb(191, 97, 106),
+                warning: rgb(235, 203, 139),
+                success: rgb(163, 190, 140),
+                muted: rgb(129, 161, 193),
+                prompt_prefix: "›".to_string(),
+                border: BorderStyle::Rounded,
+                spinner: "Arc".to_string(),
+            },
+            "gruvbox" => Theme {
+                primary: rgb(254, 128, 25),
+                accent: rgb(184, 187, 38),
+                error: rgb(251, 73, 52),
+                warning: rgb(250, 189, 47),
+                success: rgb(184, 187, 38),
+                muted: rgb(146, 131, 116),
+                border: BorderStyle::Thick,
+                spinner: "Line".to_string(),
+                ..Theme::default()
+            },
+            _ => return None,
+        };
+        Some(theme)
+    }
+
+    /// Loads a theme from a TOML, JSON or YAML file.
+    ///
+    /// ```toml
+    /// base = "nord"           # a preset to start from, "default" if absent
+    /// primary = "#b48ead"     # names like "cyan", "ansi_(81)" or "rgb_(1,2,3)" work too
+    /// prompt_prefix = "$"
+    /// border = "double"       # sharp, rounded, double, thick or ascii
+    /// spinner = "Dots3"       # one of `SPINNER_STYLES`
+    /// ```
+    pub fn from_file(path: &Path) -> io::Result<Theme> {
+        let format = Format::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unsupported theme file: {}", path.display()),
+            )
+        })?;
+        Theme::parse(&fs::read_to_string(path)?, format)
+    }
+
+    pub fn parse(content: &str, format: Format) -> io::Result<Theme> {
+        let file: ThemeFile = match format {
+            Format::Toml => toml::from_str(content).map_err(invalid_data)?,
+            Format::Json => serde_json::from_str(content).map_err(invalid_data)?,
+            Format::Yaml => serde_yaml::from_str(content).map_err(invalid_data)?,
+        };
+        file.into_theme()
+    }
+
+    /// The theme with ASCII borders, spinner and prompt prefix, for
+    /// terminals that can't show more.
+    pub fn ascii(mut self) -> Self {
+        self.border = BorderStyle::Ascii;
+        if !ASCII_SPINNERS.contains(&self.spinner.as_str()) {
+            self.spinner = "Line".to_string();
+        }
+        if !self.prompt_prefix.is_ascii() {
+            self.prompt_prefix = ">".to_string();
+        }
+        self
+    }
+}
+
+fn rgb(r: u8, g: u8, b: u8) -> Color {
+    Color::Rgb { r, g, b }
+}
+
+/// The lines drawn around tables and boxed headers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BorderStyle {
+    #[default]
+    Sharp,
+    Rounded,
+    Double,
+    Thick,
+    Ascii,
+}
+
+/// The glyphs of a `BorderStyle`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Border {
+    pub horizontal: &'static str,
+    pub vertical: &'static str,
+    pub top_left: &'static str,
+    pub top_right: &'static str,
+    pub bottom_left: &'static str,
+    pub bottom_right: &'static str,
+    // Where a horizontal rule meets the left and right sides
+    pub left_tee: &'static str,
+    pub right_tee: &'static str,
+}
+
+impl BorderStyle {
+    pub fn glyphs(self) -> Border {
+        let [horizontal, vertical, top_left, top_right, bottom_left, bottom_right, left_tee, right_tee] =
+            match self {
+                BorderStyle::Sharp => ["─", "│", "┌", "┐", "└", "┘", "├", "┤"],
+                BorderStyle::Rounded => ["─", "│", "╭", "╮", "╰", "╯", "├", "┤"],
+                BorderStyle::Double => ["═", "║", "╔", "╗", "╚", "╝", "╠", "╣"],
+                BorderStyle::Thick => ["━", "┃", "┏", "┓", "┗", "┛", "┣", "┫"],
+                BorderStyle::Ascii => ["-", "|", "+", "+", "+", "+", "+", "+"],
+            };
+        Border {
+            horizontal,
+            vertical,
+            top_left,
+            top_right,
+            bottom_left,
+            bottom_right,
+            left_tee,
+            right_tee,
+        }
+    }
+}
+
+// A theme as written in a file, every field overriding the base preset
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+    base: Option<String>,
+    primary: Option<Color>,
+    accent: Option<Color>,
+    error: Option<Color>,
+    warning: Option<Color>,
+    success: Option<Color>,
+    muted: Option<Color>,
+    prompt_prefix: Option<String>,
+    border: Option<BorderStyle>,
+    spinner: Option<String>,
+}
+
+impl ThemeFile {
+    fn into_theme(self) -> io::Result<Theme> {
+        let base = self.base.as_deref().unwrap_or("default");
+        let mut theme = Theme::preset(base)
+            .ok_or_else(|| invalid_theme(format!("Unknown base theme '{}'", base)))?;
+
+        theme.primary = self.primary.unwrap_or(theme.primary);
+        theme.accent = self.accent.unwrap_or(theme.accent);
+        theme.error = self.error.unwrap_or(theme.error);
+        theme.warning = self.warning.unwrap_or(theme.warning);
+        theme.success = self.success.unwrap_or(theme.success);
+        theme.muted = self.muted.unwrap_or(theme.muted);
+        theme.prompt_prefix = self.prompt_prefix.unwrap_or(theme.prompt_prefix);
+        theme.border = self.border.unwrap_or(theme.border);
+        if let Some(style) = self.spinner {
+            let style = known_spinner(&style)
+                .ok_or_else(|| invalid_theme(format!("Unknown spinner style '{}'", style)))?;
+            theme.spinner = style.to_string();
+        }
+        Ok(theme)
+    }
+}
+
+fn invalid_data<E: std::error::Error + Send + Sync + 'static>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+fn invalid_theme(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+static THEME: Mutex<Option<Theme>> = Mutex::new(None);
+
+thread_local! {
+    // The theme of the app run by this thread, which wins over the
+    // process-wide one when apps run side by side with `run_from`
+    static LOCAL_THEME: RefCell<Option<Theme>> = const { RefCell::new(None) };
+}
+
+/// Makes every widget created from now on use `theme`.
+pub fn set_theme(theme: Theme) {
+    *THEME.lock().unwrap() = Some(theme);
+}
+
+/// The theme widgets pick up when they are created, in ASCII if the
+/// terminal can't show more.
+pub fn theme() -> Theme {
+    let theme = LOCAL_THEME
+        .with(|local| local.borrow().clone())
+        .or_else(|| THEME.lock().unwrap().clone())
+        .unwrap_or_default();
+    if supports_unicode() {
+        theme
+    } else {
+        theme.ascii()
+    }
+}
+
+// Returns the theme it replaces, so callers can put it back
+pub(crate) fn replace_local_theme(theme: Option<Theme>) -> Option<Theme> {
+    LOCAL_THEME.with(|local| local.replace(theme))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_theme_file_overrides_its_base() {
+        let theme = Theme::parse(
+            r##"
+            base = "nord"
+            primary = "#ff0000"
+            border = "double"
+            spinner = "dots12"
+            "##,
+            Format::Toml,
+        )
+        .unwrap();
+
+        let nord = Theme::preset("nord").unwrap();
+        assert_eq!(theme.primary, rgb(255, 0, 0));
+        assert_eq!(theme.accent, nord.accent);
+        assert_eq!(theme.prompt_prefix, "›");
+        assert_eq!(theme.border.glyphs().top_left, "╔");
+        assert_eq!(theme.spinner, "Dots12");
+    }
+
+    #[test]
+    fn test_theme_file_errors() {
+        for content in [
+            r#"base = "nope""#,
+            r#"spinner = "nope""#,
+            r#"primary = "not a color""#,
+            r#"prefix = ">""#,
+            r#"border = "dotted""#,
+        ] {
+            let err = Theme::parse(content, Format::Toml).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{}", content);
+        }
+        // rustubble panics on a spinner style it doesn't know
+        assert!(PRESETS.iter().all(|name| {
+            let theme = Theme::preset(name).unwrap();
+            known_spinner(&theme.spinner) == Some(theme.spinner.as_str())
+        }));
+    }
+
+    #[test]
+    fn test_ascii_theme() {
+        let theme = Theme::preset("dracula").unwrap().ascii();
+        assert_eq!(theme.prompt_prefix, ">");
+        assert_eq!(theme.border, BorderStyle::Ascii);
+        assert_eq!(theme.spinner, "Line");
+    }
+}
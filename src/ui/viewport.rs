@@ -0,0 +1,124 @@
+use super::{dismiss, theme, Frame, Span, Step, Theme, Widget};
+use crossterm::event::{KeyCode, KeyEvent};
+
+/// Scrolls through text wrapped to a width, under a boxed header.
+#[derive(Clone, Debug)]
+pub(crate) struct Viewport {
+    header: String,
+    lines: Vec<String>,
+    height: usize,
+    width: usize,
+    scroll: usize,
+    theme: Theme,
+}
+
+impl Viewport {
+    pub fn new(header: impl Into<String>, content: &str, height: usize, width: usize) -> Self {
+        Viewport {
+            header: header.into(),
+            lines: wrap(content, width.max(1)),
+            height: height.max(1),
+            width,
+            scroll: 0,
+            theme: theme(),
+        }
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.lines.len().saturating_sub(self.height)
+    }
+}
+
+// Breaks text into lines of at most `width` chars, at spaces when it can
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let mut word: Vec<char> = word.chars().collect();
+            // Words too long for a line are cut
+            while word.len() > width {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                lines.push(word.drain(..width).collect());
+            }
+            let len = line.chars().count();
+            if len > 0 && len + 1 + word.len() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.extend(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+impl Widget for Viewport {
+    type Output = ();
+
+    fn handle_key(&mut self, key: KeyEvent) -> Step<()> {
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.scroll = self.max_scroll().min(self.scroll + 1)
+            }
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(self.height),
+            KeyCode::PageDown => self.scroll = self.max_scroll().min(self.scroll + self.height),
+            _ => return dismiss(&key),
+        }
+        Step::Continue
+    }
+
+    fn render(&self) -> Frame {
+        let (theme, border) = (&self.theme, self.theme.border.glyphs());
+        let rule = border.horizontal.repeat(self.header.chars().count() + 2);
+        let header = format!("{} {} {}", border.vertical, self.header, border.vertical);
+        let mut frame = Frame::new();
+        frame
+            .line(
+                Span::new(border.top_left.to_string() + &rule + border.top_right).fg(theme.primary),
+            )
+            .line(Span::new(header).fg(theme.primary).bold())
+            .line(
+                Span::new(border.bottom_left.to_string() + &rule + border.bottom_right)
+                    .fg(theme.primary),
+            )
+            .blank();
+        for index in self.scroll..self.scroll + self.height {
+            frame.line(Span::new(
+                self.lines.get(index).cloned().unwrap_or_default(),
+            ));
+        }
+        let percent = match self.max_scroll() {
+            0 => 100.0,
+            max => self.scroll as f32 / max as f32 * 100.0,
+        };
+        frame.blank().line(
+            Span::new(format!(
+                "{} {:.0}%",
+                border.horizontal.repeat(self.width.saturating_sub(5)),
+                percent
+            ))
+            .fg(theme.muted),
+        );
+        frame
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(
+            wrap("the quick brown fox\n\njumps", 9),
+            vec!["the quick", "brown fox", "", "jumps"]
+        );
+        assert_eq!(wrap("abcdefgh ij", 3), vec!["abc", "def", "gh", "ij"]);
+    }
+}
@@ -1,117 +1,238 @@
-use std::fs;
+use std::cell::RefCell;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::future::Future;
 use std::io::{self, Write};
-use std::thread::sleep;
-use std::time::Duration;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crossterm::execute;
 use crossterm::style::Color;
-use crossterm::terminal::{disable_raw_mode, enable_raw_mode, Clear, ClearType};
-use rustubble::input::{handle_input, TextInput};
-use rustubble::list::{handle_list, Item, ItemList};
-use rustubble::menu_list::{handle_menu_list, Menu};
-use rustubble::progress_bar::{handle_progress_bar, ProgressBar};
-use rustubble::spinner::{handle_spinner, Spinner};
-use rustubble::stopwatch::{handle_stopwatch, StopWatch};
-use rustubble::table::{handle_table, Table};
-use rustubble::text_area::{handle_text_area, TextArea};
-use rustubble::timer::{handle_timer, Timer};
-use rustubble::viewport::{handle_viewport, Viewport};
+use rustubble::list::Item;
 
-pub fn input_form(
-    placeholder: &str,
-    initial_text: &str,
-    label: &str,
-) -> Result<Option<String>, io::Error> {
-    // Flush stdout to ensure all previous logs are written to the terminal
-    io::stdout().flush().unwrap();
+use crate::context::{GlobalFlags, OutputFormat};
+use crate::signals::cancelled;
+use crate::ui::{self, Prompt, View};
 
-    enable_raw_mode().unwrap();
+// Flags for code running outside of a command, see `set_global_flags`
+static GLOBAL_FLAGS: Mutex<Option<GlobalFlags>> = Mutex::new(None);
 
-    let mut text_input = TextInput::new(
-        Some(placeholder),      // Placeholder
-        2,                      // Padding
-        initial_text,           // Initial text
-        label,                  // Label
-        Some("Ctrl+C to exit"), // Helper text
-        ">",                    // Prefix
-    );
-
-    let x = 5;
-    let y = 5;
-
-    execute!(std::io::stdout(), Clear(ClearType::All)).unwrap();
-    let text_input = handle_input(&mut text_input, x, y);
-    let text_2 = format!("Input value: {:?}", text_input);
-
-    disable_raw_mode().unwrap();
-    Ok(Some(text_2))
+thread_local! {
+    // The flags of the command run by this thread, which win over the
+    // process-wide ones when apps run side by side with `run_from`
+    static LOCAL_FLAGS: RefCell<Option<GlobalFlags>> = const { RefCell::new(None) };
 }
 
-pub fn text_area(label: &str, visible_lines: usize) -> Result<(), io::Error> {
-    io::stdout().flush().unwrap();
-
-    enable_raw_mode().unwrap();
-
-    let mut text_area = TextArea::new(label, Some("Press ESC to exit."), visible_lines);
-    // text_area.render(0, 1); // Initial render at position (0, 1)
-
-    let x = 5;
-    let y = 5;
-
-    execute!(std::io::stdout(), Clear(ClearType::All)).unwrap();
-    let text_area_value = handle_text_area(&mut text_area, x, y);
+/// Sets the flags the wrappers follow outside of a run, as when an app calls
+/// them without `execute_cli`. A run follows the flags of its command line.
+pub fn set_global_flags(flags: GlobalFlags) {
+    ui::set_color_choice(flags.color);
+    *GLOBAL_FLAGS.lock().unwrap() = Some(flags);
+}
 
-    let text_2 = format!("Input value: {:?}", text_area_value);
+// Sets the flags of the run on this thread, its colors included
+pub(crate) fn set_local_flags(flags: GlobalFlags) {
+    ui::replace_local_color_choice(Some(flags.color));
+    replace_local_flags(Some(flags));
+}
 
-    println!("{}", text_2);
+// Returns the flags it replaces, so callers can put them back
+pub(crate) fn replace_local_flags(flags: Option<GlobalFlags>) -> Option<GlobalFlags> {
+    LOCAL_FLAGS.with(|local| local.replace(flags))
+}
 
-    disable_raw_mode().unwrap();
+pub(crate) fn global_flags() -> GlobalFlags {
+    if let Some(flags) = LOCAL_FLAGS.with(|local| local.borrow().clone()) {
+        return flags;
+    }
+    GLOBAL_FLAGS.lock().unwrap().clone().unwrap_or_default()
+}
 
+// Prompts fail with `--no-input` rather than wait for a user
+fn check_input_allowed() -> io::Result<()> {
+    if global_flags().no_input {
+        return Err(io::Error::other(
+            "input is required, but --no-input was given",
+        ));
+    }
     Ok(())
 }
 
-pub fn loader(message: String, style: String) -> Result<(), io::Error> {
-    io::stdout().flush().unwrap();
+pub fn input_form(
+    placeholder: &str,
+    initial_text: &str,
+    label: &str,
+) -> Result<Option<String>, io::Error> {
+    check_input_allowed()?;
+    let prefix = ui::theme().prompt_prefix;
+    let input = ui::prompt(&Prompt::Input {
+        label,
+        placeholder,
+        initial_text,
+        prefix: &prefix,
+        helper: "Ctrl+C to exit",
+    })?;
+
+    // The prompts return nothing when the user pressed Ctrl+C
+    input.map(Some).ok_or_else(cancelled)
+}
 
-    let spinner = Spinner::new(
-        Color::Rgb {
-            r: 0,
-            g: 255,
-            b: 255,
-        },
-        message,
-        &style,
-    );
+/// Options of `text_area_with` beyond the label and size.
+#[derive(Clone, Debug)]
+pub struct TextAreaOptions {
+    pub initial_text: String,
+    // In chars, counting newlines
+    pub max_length: Option<usize>,
+    pub max_lines: Option<usize>,
+    // Adds `--editor` to commands, to write the text with `open_editor`
+    pub editor: bool,
+}
 
-    let (x, y) = (10, 10);
-    handle_spinner(&spinner, x, y);
+impl Default for TextAreaOptions {
+    fn default() -> Self {
+        TextAreaOptions {
+            initial_text: String::new(),
+            max_length: None,
+            max_lines: None,
+            editor: true,
+        }
+    }
+}
 
-    execute!(std::io::stdout(), Clear(ClearType::All)).unwrap();
-    println!("Operation completed.");
+impl TextAreaOptions {
+    // Fails for text given some other way than the text area, like `--text`
+    pub(crate) fn check(&self, text: &str) -> io::Result<()> {
+        let invalid = |message| Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+        if let Some(max_length) = self.max_length {
+            if text.chars().count() > max_length {
+                return invalid(format!("text is longer than {} characters", max_length));
+            }
+        }
+        if let Some(max_lines) = self.max_lines {
+            // Counted like the text area does, so a trailing newline is a line
+            if text.split('\n').count() > max_lines {
+                return invalid(format!("text has more than {} lines", max_lines));
+            }
+        }
+        Ok(())
+    }
 
-    Ok(())
+    // The help line of the text area, with the limits to respect
+    fn helper(&self) -> String {
+        let limits: Vec<String> = [(self.max_length, "characters"), (self.max_lines, "lines")]
+            .iter()
+            .filter_map(|(limit, unit)| limit.map(|limit| format!("{} {}", limit, unit)))
+            .collect();
+        let keys = "Tab to submit, Esc to exit.";
+        if limits.is_empty() {
+            return keys.to_string();
+        }
+        format!("Up to {}. {}", limits.join(" and "), keys)
+    }
 }
 
-pub fn table(headers: Vec<&'static str>, rows: Vec<Vec<&'static str>>) -> Result<(), io::Error> {
-    io::stdout().flush().unwrap();
+pub fn text_area(label: &str, visible_lines: usize) -> Result<Option<String>, io::Error> {
+    text_area_with(label, visible_lines, &TextAreaOptions::default())
+}
 
-    enable_raw_mode().unwrap();
+/// Prompts for multi-line text. Text past a limit is handed back to the
+/// user with the reason, until it fits.
+pub fn text_area_with(
+    label: &str,
+    visible_lines: usize,
+    options: &TextAreaOptions,
+) -> Result<Option<String>, io::Error> {
+    check_input_allowed()?;
+    let mut text = options.initial_text.clone();
+    let mut helper = options.helper();
+    loop {
+        text = ui::prompt(&Prompt::TextArea {
+            label,
+            initial_text: &text,
+            visible_lines,
+            helper: &helper,
+        })?
+        .ok_or_else(cancelled)?;
+        match options.check(&text) {
+            Ok(()) => return Ok(Some(text)),
+            Err(err) => helper = format!("Sorry, the {}. {}", err, options.helper()),
+        }
+    }
+}
 
-    let headers_str: Vec<String> = headers.iter().map(|&s| s.to_string()).collect();
-    let rows_str: Vec<Vec<String>> = rows
+/// Opens `path` in `$VISUAL` or `$EDITOR`, `vi` if neither is set, and
+/// waits for the editor to exit. The variables can hold args, as in
+/// `EDITOR="code --wait"`.
+pub fn edit_file(path: &Path) -> io::Result<()> {
+    let editor = ["VISUAL", "EDITOR"]
         .iter()
-        .map(|row| row.iter().map(|&s| s.to_string()).collect())
-        .collect();
-
-    let mut table = Table::new(headers_str, rows_str, 0, 3, 5);
+        .find_map(|name| env::var(name).ok().filter(|value| !value.trim().is_empty()))
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or_default();
+
+    let status = Command::new(program).args(words).arg(path).status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "editor '{}' exited with {}",
+            editor, status
+        )));
+    }
+    Ok(())
+}
 
-    let (x, y) = (5, 5);
-    execute!(std::io::stdout(), Clear(ClearType::All)).unwrap();
-    handle_table(&mut table, x, y);
+/// Lets the user edit `text` in `$VISUAL` or `$EDITOR`, through a temporary
+/// file, and returns what they saved.
+pub fn open_editor(text: &str) -> io::Result<String> {
+    let path = env::temp_dir().join(format!(
+        "bubblers-{}-{}.txt",
+        std::process::id(),
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+    ));
+    // Fails rather than follow a file or link put there by someone else
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    file.write_all(text.as_bytes())?;
+    drop(file);
+    let edited = edit_file(&path).and_then(|_| fs::read_to_string(&path));
+    let _ = fs::remove_file(&path);
+
+    // Editors end the last line with a newline the user didn't type
+    let mut edited = edited?;
+    if edited.ends_with('\n') {
+        edited.pop();
+        if edited.ends_with('\r') {
+            edited.pop();
+        }
+    }
+    Ok(edited)
+}
 
-    disable_raw_mode().unwrap();
+/// Shows a spinner until Esc. An empty or unknown style keeps the spinner
+/// of the theme.
+pub fn loader(message: String, style: String) -> Result<(), io::Error> {
+    let theme = ui::theme();
+    ui::show(&View::Spinner {
+        message: &message,
+        style: ui::known_spinner(&style).unwrap_or(&theme.spinner),
+        color: theme.accent,
+    })?;
+
+    report("Operation completed.".to_string())
+}
 
-    Ok(())
+pub fn table(headers: Vec<String>, rows: Vec<Vec<String>>) -> Result<(), io::Error> {
+    ui::show(&View::Table {
+        headers: &headers,
+        rows: &rows,
+    })
 }
 
 pub fn timed_progress(
@@ -121,85 +242,44 @@ pub fn timed_progress(
     start_color: Color,
     end_color: Color,
 ) -> Result<(), io::Error> {
-    io::stdout().flush().unwrap();
-
-    enable_raw_mode().unwrap();
-
-    let mut progress_bar =
-        ProgressBar::new(prefix.to_string(), progress, length, start_color, end_color);
-    let (x, y) = (10, 10);
-    execute!(std::io::stdout(), Clear(ClearType::All)).unwrap();
-
-    for i in 0..=100 {
-        handle_progress_bar(&mut progress_bar, i as f32 / 100.0, x, y);
-
-        // progress_bar.update(i);
-        sleep(Duration::from_millis(10)); // Simulate time-consuming task
-    }
-
-    disable_raw_mode().unwrap();
-
-    Ok(())
+    ui::show(&View::Progress {
+        prefix,
+        progress,
+        length,
+        start_color,
+        end_color,
+    })
 }
 
 pub fn timer(secs: u64, nanos: u32) -> Result<(), io::Error> {
-    io::stdout().flush().unwrap();
-    enable_raw_mode().unwrap();
-
-    let duration = Duration::new(secs, nanos); // For example, 5 minutes
-    let mut timer = Timer::new(duration);
-
-    let (x, y) = (5, 5);
-    execute!(std::io::stdout(), Clear(ClearType::All)).unwrap();
-    handle_timer(&mut timer, x, y);
-    disable_raw_mode().unwrap();
-    Ok(())
+    let duration = Duration::new(secs, nanos);
+    ui::show(&View::Timer { duration })
 }
 
 pub fn stopwatch() -> Result<(), io::Error> {
-    io::stdout().flush().unwrap();
-    enable_raw_mode().unwrap();
-    let mut time = StopWatch::new();
-    let (x, y) = (5, 5);
-    execute!(std::io::stdout(), Clear(ClearType::All)).unwrap();
-    handle_stopwatch(&mut time, x, y);
-    disable_raw_mode().unwrap();
-    Ok(())
+    ui::show(&View::Stopwatch)
 }
 
 pub fn viewport(file_path: String) -> Result<(), io::Error> {
-    io::stdout().flush().unwrap();
-    enable_raw_mode().unwrap();
-    let file_name = file_path.split('/').last().unwrap();
+    let file_name = file_path.split('/').next_back().unwrap();
     let header = file_name.to_string();
 
-    let content = fs::read_to_string(file_path).expect("Should have been able to read the file");
-
-    let height = 20; // Assume the visible height of the content area is 5 lines
-    let width = 100; // Assume the visible width of the content area is 10 characters
-
-    let x = 5;
-    let y = 5;
+    let content = fs::read_to_string(&file_path)?;
 
-    let mut viewport = Viewport::new(header, content, height, width, 6);
-    execute!(std::io::stdout(), Clear(ClearType::All)).unwrap();
-    handle_viewport(&mut viewport, x, y);
-    disable_raw_mode().unwrap();
-    Ok(())
+    ui::show(&View::Viewport {
+        header: &header,
+        content: &content,
+    })
 }
 
 pub fn item_list(list: Vec<Item>, list_title: String) -> Result<Option<String>, io::Error> {
-    io::stdout().flush().unwrap();
-    enable_raw_mode().unwrap();
-    let mut list = ItemList::new(list_title, list);
-
-    let (x, y) = (5, 5);
-    let list = handle_list(&mut list, x, y);
+    check_input_allowed()?;
+    let list = ui::prompt(&Prompt::List {
+        title: &list_title,
+        items: &list,
+    })?;
 
-    execute!(std::io::stdout(), Clear(ClearType::All)).unwrap();
-    println!("List: {:?}", list);
-    disable_raw_mode().unwrap();
-    Ok(list)
+    list.map(Some).ok_or_else(cancelled)
 }
 
 pub fn menu_list(
@@ -207,16 +287,93 @@ pub fn menu_list(
     list_title: String,
     list_sub_title: String,
 ) -> Result<Option<String>, io::Error> {
-    io::stdout().flush().unwrap();
-    enable_raw_mode().unwrap();
-    let mut list = Menu::new(list_title, list_sub_title, list);
+    check_input_allowed()?;
+    let selected_menu = ui::prompt(&Prompt::Menu {
+        title: &list_title,
+        subtitle: &list_sub_title,
+        options: &list,
+    })?;
+
+    selected_menu.map(Some).ok_or_else(cancelled)
+}
+
+/// Shows a spinner through the backend while `future` runs, then clears
+/// it. Nothing shows with `--quiet`.
+pub async fn spin_while<F: Future>(message: impl Into<String>, future: F) -> F::Output {
+    if global_flags().quiet {
+        return future.await;
+    }
+    let theme = ui::theme();
+    let message = message.into();
+    let interval = ui::spinner_frames(&theme.spinner)
+        .map_or(Duration::from_millis(80), |(_, interval)| interval);
+    let live = ui::show_live(interval, move |backend, frame| {
+        backend.show(&View::LiveSpinner {
+            message: &message,
+            style: &theme.spinner,
+            color: theme.accent,
+            frame,
+        })
+    });
+
+    let output = future.await;
+    drop(live);
+    output
+}
 
-    let (x, y) = (5, 5);
+/// Progress shown by `progress_while`, from 0.0 to 1.0.
+#[derive(Clone, Debug, Default)]
+pub struct ProgressHandle(Arc<AtomicU32>);
 
-    let selected_menu = handle_menu_list(&mut list, x, y);
+impl ProgressHandle {
+    pub fn set(&self, progress: f32) {
+        self.0
+            .store(progress.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
+    }
 
-    execute!(std::io::stdout(), Clear(ClearType::All)).unwrap();
-    println!("List: {:?}", selected_menu);
-    disable_raw_mode().unwrap();
-    Ok(selected_menu)
+    pub fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// Shows a progress bar through the backend while the future built by
+/// `task` runs, then clears it. The task reports its progress through the
+/// handle it is given. Nothing shows with `--quiet`.
+pub async fn progress_while<T, Fut>(prefix: impl Into<String>, task: T) -> Fut::Output
+where
+    T: FnOnce(ProgressHandle) -> Fut,
+    Fut: Future,
+{
+    let handle = ProgressHandle::default();
+    if global_flags().quiet {
+        return task(handle).await;
+    }
+
+    let theme = ui::theme();
+    let (prefix, progress) = (prefix.into(), handle.clone());
+    let live = ui::show_live(Duration::from_millis(50), move |backend, _| {
+        backend.show(&View::LiveProgress {
+            prefix: &prefix,
+            progress: progress.get(),
+            length: 40,
+            start_color: theme.primary,
+            end_color: theme.accent,
+        })
+    });
+
+    let output = task(handle).await;
+    drop(live);
+    output
+}
+
+// Prints a status line through the UI backend, unless quiet or printing
+// JSON, which must be all of stdout
+fn report(line: String) -> io::Result<()> {
+    let flags = global_flags();
+    if flags.quiet || flags.output == OutputFormat::Json {
+        return Ok(());
+    }
+    let backend = ui::backend();
+    let result = backend.lock().unwrap().print(&line);
+    result
 }